@@ -0,0 +1,91 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Fields};
+
+/// Derive `transform::FixedSize` and `transform::BinLayout` for a struct whose
+/// fields are all `FixedSize`.
+///
+/// The layout is that of the default configuration: a u32 frame followed by
+/// the fields in order. Newtype structs are transparent and unit structs take
+/// a single byte. Serde attributes that change the encoded fields, like
+/// `skip` or `flatten`, are not taken into account.
+#[proc_macro_derive(BinLayout)]
+pub fn derive_bin_layout(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(mut input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "BinLayout can only be derived for structs",
+            ))
+        }
+    };
+
+    let names: Vec<String> = match fields {
+        Fields::Named(fields) => fields
+            .named
+            .iter()
+            .map(|field| field.ident.as_ref().unwrap().unraw().to_string())
+            .collect(),
+        _ => (0..fields.len()).map(|index| index.to_string()).collect(),
+    };
+    let types: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+    let sizes: Vec<TokenStream2> = types
+        .iter()
+        .map(|ty| quote!(<#ty as ::transform::FixedSize>::SIZE))
+        .collect();
+    let descriptors: Vec<TokenStream2> = types
+        .iter()
+        .map(|ty| quote!(<#ty as ::transform::FixedSize>::DESCRIPTOR))
+        .collect();
+
+    // Newtype structs are encoded as their field, unit structs as a unit,
+    // and all other structs inside a u32 frame
+    let (frame, size) = match fields {
+        Fields::Unnamed(_) if fields.len() == 1 => (quote!(0), quote!(0)),
+        Fields::Unit => (quote!(0), quote!(1)),
+        _ => (quote!(4), quote!(4)),
+    };
+    let offsets: Vec<TokenStream2> = (0..sizes.len())
+        .map(|index| {
+            let before = &sizes[..index];
+            quote!(#frame #(+ #before)*)
+        })
+        .collect();
+
+    let where_clause = input.generics.make_where_clause();
+    for ty in &types {
+        where_clause
+            .predicates
+            .push(parse_quote!(#ty: ::transform::FixedSize));
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::transform::FixedSize for #name #ty_generics #where_clause {
+            const SIZE: usize = #size #(+ #sizes)*;
+            const DESCRIPTOR: u32 = <Self as ::transform::BinLayout>::FINGERPRINT;
+        }
+
+        impl #impl_generics ::transform::BinLayout for #name #ty_generics #where_clause {
+            const FIELDS: &'static [::transform::FieldLayout] = &[
+                #(::transform::FieldLayout {
+                    name: #names,
+                    offset: #offsets,
+                    size: #sizes,
+                    descriptor: #descriptors,
+                },)*
+            ];
+        }
+    })
+}
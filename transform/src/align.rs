@@ -0,0 +1,74 @@
+// Natural alignment bookkeeping for the C layout.
+//
+// A container has to start at a multiple of its largest field alignment,
+// which is only known once all of its fields have been visited. Encoding
+// therefore runs twice: a measuring pass records the alignment of every
+// container in the order they are opened, and the second pass replays those
+// alignments to place padding exactly where a C compiler would. Decoding has
+// no value to measure, so its measuring pass walks the type instead, reading
+// every primitive as 1 without touching the input. Every type the C layout
+// accepts has the same shape whatever its value.
+#[derive(Default)]
+pub(crate) struct Alignments {
+    // Alignment of every container, in the order they are opened
+    aligns: Vec<usize>,
+    // Indices into `aligns` of the containers currently being visited
+    open: Vec<usize>,
+    // Index of the next container to open once measured
+    next: usize,
+    measured: bool,
+}
+
+impl Alignments {
+    pub(crate) fn is_measured(&self) -> bool {
+        self.measured
+    }
+
+    // Switch from the measuring pass to the encoding/decoding pass
+    pub(crate) fn finish_measuring(&mut self) {
+        self.open.clear();
+        self.next = 0;
+        self.measured = true;
+    }
+
+    // Returns the alignment the container must start at
+    pub(crate) fn open(&mut self) -> usize {
+        if self.measured {
+            let index = self.next;
+            self.next += 1;
+            self.open.push(index);
+            self.aligns.get(index).copied().unwrap_or(1)
+        } else {
+            self.aligns.push(1);
+            self.open.push(self.aligns.len() - 1);
+            1
+        }
+    }
+
+    pub(crate) fn primitive(&mut self, size: usize) {
+        if !self.measured {
+            if let Some(&index) = self.open.last() {
+                self.aligns[index] = self.aligns[index].max(size);
+            }
+        }
+    }
+
+    // Returns the alignment the container must be padded to at its end
+    pub(crate) fn close(&mut self) -> usize {
+        let align = match self.open.pop() {
+            Some(index) => self.aligns[index],
+            None => 1,
+        };
+        if !self.measured {
+            if let Some(&parent) = self.open.last() {
+                self.aligns[parent] = self.aligns[parent].max(align);
+            }
+        }
+        align
+    }
+}
+
+// Number of padding bytes needed to move `position` to a multiple of `align`
+pub(crate) fn padding(position: usize, align: usize) -> usize {
+    (align - position % align) % align
+}
@@ -0,0 +1,65 @@
+//! Encoding to and decoding from `futures::io` streams.
+use crate::de::{decode_framed, frame_header_len, frame_len};
+use crate::{to_bytes_with_config, Config, Error, Result};
+use futures_util::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use serde::de::DeserializeOwned;
+use serde::ser::Serialize;
+
+pub async fn to_async_writer<W, T>(writer: &mut W, value: &T) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    to_async_writer_with_config(writer, value, Config::default()).await
+}
+
+pub async fn to_async_writer_with_config<W, T>(
+    writer: &mut W,
+    value: &T,
+    config: Config,
+) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let bytes = to_bytes_with_config(value, config)?;
+    writer.write_all(&bytes).await?;
+    Ok(())
+}
+
+/// Decode one value, reading exactly the bytes its outer frame covers. The
+/// top-level value must be framed, as for `IncrementalDecoder`.
+pub async fn from_async_reader<R, T>(reader: R) -> Result<T>
+where
+    R: AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    from_async_reader_with_config(reader, Config::default()).await
+}
+
+pub async fn from_async_reader_with_config<R, T>(mut reader: R, config: Config) -> Result<T>
+where
+    R: AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    let header_len = frame_header_len(config)?;
+    let mut bytes = Vec::new();
+    read_exact(&mut reader, header_len, &mut bytes).await?;
+    let total = frame_len(&bytes, config)?.ok_or(Error::UnexpectedEof)?;
+    read_exact(&mut reader, total - header_len, &mut bytes).await?;
+    decode_framed(&bytes, config)
+}
+
+// Append exactly `len` bytes to `bytes`
+async fn read_exact<R>(reader: &mut R, len: usize, bytes: &mut Vec<u8>) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+{
+    // `read_to_end` only allocates for bytes that actually arrive, so a
+    // bogus length can't make it reserve a huge buffer
+    let read = reader.take(len as u64).read_to_end(bytes).await?;
+    if read < len {
+        return Err(Error::UnexpectedEof);
+    }
+    Ok(())
+}
@@ -0,0 +1,48 @@
+//! Canonical encoding of sets.
+//!
+//! Sets encode as seqs, which the canonical mode has to keep in order. Use
+//! this module on set fields with `#[serde(with = "transform::canonical_set")]`
+//! to have their elements sorted by their encoded bytes instead, and checked
+//! for order when decoding. Other formats see the set unchanged.
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use std::fmt;
+use std::marker::PhantomData;
+
+// Newtype name the serializer and deserializer recognize sets by
+pub(crate) const SET: &str = "$transform::canonical_set";
+
+pub fn serialize<S, C>(set: &C, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    C: Serialize,
+{
+    serializer.serialize_newtype_struct(SET, set)
+}
+
+pub fn deserialize<'de, D, C>(deserializer: D) -> Result<C, D::Error>
+where
+    D: Deserializer<'de>,
+    C: Deserialize<'de>,
+{
+    deserializer.deserialize_newtype_struct(SET, SetVisitor(PhantomData))
+}
+
+struct SetVisitor<C>(PhantomData<C>);
+
+impl<'de, C: Deserialize<'de>> de::Visitor<'de> for SetVisitor<C> {
+    type Value = C;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a set")
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, deserializer: D) -> Result<C, D::Error> {
+        C::deserialize(deserializer)
+    }
+
+    // Formats without newtypes hand over the seq directly
+    fn visit_seq<A: de::SeqAccess<'de>>(self, seq: A) -> Result<C, A::Error> {
+        C::deserialize(de::value::SeqAccessDeserializer::new(seq))
+    }
+}
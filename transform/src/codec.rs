@@ -0,0 +1,116 @@
+use crate::de::decode_exact;
+use crate::{to_bytes_with_config, Config, Error, Result};
+use bytes::{Buf, BufMut, BytesMut};
+use serde::de::DeserializeOwned;
+use serde::ser::Serialize;
+use std::marker::PhantomData;
+use tokio_util::codec::{Decoder, Encoder};
+
+// Size of the u32 length prefix in front of every frame
+const HEADER_LEN: usize = 4;
+
+/// Frames values of type `T` on a byte stream, for use with
+/// `tokio_util::codec::Framed`.
+///
+/// Every value is encoded with `to_bytes` and preceded by its length as a
+/// little-endian u32, the same prefix the crate uses for its own frames.
+pub struct TransformCodec<T> {
+    config: Config,
+    max_frame_size: usize,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<T> TransformCodec<T> {
+    /// Frames larger than this are rejected unless configured otherwise.
+    pub const DEFAULT_MAX_FRAME_SIZE: usize = 8 * 1024 * 1024;
+
+    pub fn new() -> Self {
+        Self::with_config(Config::default())
+    }
+
+    pub fn with_config(config: Config) -> Self {
+        TransformCodec {
+            config,
+            max_frame_size: Self::DEFAULT_MAX_FRAME_SIZE,
+            value: PhantomData,
+        }
+    }
+
+    /// Reject frames whose body is longer than `max_frame_size` bytes, both
+    /// when encoding and when decoding.
+    pub fn max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size;
+        self
+    }
+
+    fn check_frame_size(&self, len: usize) -> Result<()> {
+        if len > self.max_frame_size {
+            Err(Error::FrameTooLarge(len))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<T> Default for TransformCodec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for TransformCodec<T> {
+    fn clone(&self) -> Self {
+        TransformCodec {
+            config: self.config,
+            max_frame_size: self.max_frame_size,
+            value: PhantomData,
+        }
+    }
+}
+
+impl<T: Serialize> Encoder<T> for TransformCodec<T> {
+    type Error = Error;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<()> {
+        self.encode(&item, dst)
+    }
+}
+
+// Lets values be sent without giving them up
+impl<T: Serialize> Encoder<&T> for TransformCodec<T> {
+    type Error = Error;
+
+    fn encode(&mut self, item: &T, dst: &mut BytesMut) -> Result<()> {
+        let body = to_bytes_with_config(item, self.config)?;
+        self.check_frame_size(body.len())?;
+        let len = u32::try_from(body.len()).map_err(|_| Error::FrameTooLarge(body.len()))?;
+        dst.reserve(HEADER_LEN + body.len());
+        dst.put_u32_le(len);
+        dst.extend_from_slice(&body);
+        Ok(())
+    }
+}
+
+impl<T: DeserializeOwned> Decoder for TransformCodec<T> {
+    type Item = T;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<T>> {
+        let Some(header) = src.get(..HEADER_LEN) else {
+            return Ok(None);
+        };
+        let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        // Checked before reserving so that a bad prefix can't allocate
+        self.check_frame_size(len)?;
+        let total = HEADER_LEN + len;
+        if src.len() < total {
+            src.reserve(total - src.len());
+            return Ok(None);
+        }
+
+        src.advance(HEADER_LEN);
+        let body = src.split_to(len);
+        // The value must cover the whole frame
+        decode_exact(&body, self.config).map(Some)
+    }
+}
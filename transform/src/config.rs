@@ -0,0 +1,129 @@
+/// How statically-sized containers (structs, tuples, tuple structs and enum
+/// variants) are laid out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Layout {
+    /// Every container is prefixed with a u32 byte-length frame. Structs can
+    /// gain trailing fields: older readers skip them, and newer readers fill
+    /// them from `#[serde(default)]` when they are missing. This doesn't hold
+    /// with the option bitmap, where a struct whose fields don't match its
+    /// frame is rejected.
+    #[default]
+    Framed,
+    /// Structs, tuples and enum variants are written without a frame, since
+    /// the decoder knows their field count from the type. Seqs and maps keep
+    /// their byte-length frame.
+    Packed,
+    /// Like `Packed`, but every field is padded to its natural alignment and
+    /// containers are padded to their largest field alignment, matching a
+    /// `#[repr(C)]` struct on x86_64 Linux. Units and unit structs are
+    /// zero-sized. Types without a C equivalent (seqs, maps, options, enums,
+    /// strings) are rejected.
+    C,
+}
+
+/// How the length of a sequence is prefixed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SeqLength {
+    /// A u32 byte length of the encoded elements.
+    #[default]
+    Bytes,
+    /// A u32 element count, which lets the decoder report an exact size hint
+    /// and pre-allocate.
+    Elements,
+}
+
+/// How enum variants are identified.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VariantEncoding {
+    /// The variant index as a single byte. Reordering variants changes the
+    /// meaning of stored data.
+    #[default]
+    Index,
+    /// The variant name, prefixed with its u32 byte length.
+    Name,
+    /// A u32 FNV-1a hash of the variant name.
+    Hash,
+}
+
+/// Encoding options shared by the serializer and the deserializer.
+///
+/// Both sides must use the same configuration for the bytes to round-trip.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    pub(crate) layout: Layout,
+    pub(crate) seq_length: SeqLength,
+    pub(crate) variant_encoding: VariantEncoding,
+    pub(crate) option_bitmap: bool,
+    pub(crate) self_describing: bool,
+    pub(crate) canonical: bool,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn seq_length(mut self, seq_length: SeqLength) -> Self {
+        self.seq_length = seq_length;
+        self
+    }
+
+    pub fn variant_encoding(mut self, variant_encoding: VariantEncoding) -> Self {
+        self.variant_encoding = variant_encoding;
+        self
+    }
+
+    /// Start every struct with its field count as a u16 and a bitmap holding
+    /// one bit per field, so that option fields drop their tag byte and
+    /// `None` fields, or fields skipped by `skip_serializing_if`, take no
+    /// space. Struct variants of enums are encoded as usual. Ignored in the C
+    /// layout, which has no options. Structs can't gain fields in this mode.
+    pub fn option_bitmap(mut self, option_bitmap: bool) -> Self {
+        self.option_bitmap = option_bitmap;
+        self
+    }
+
+    /// Prefix every value with a one-byte type tag, so that it can be decoded
+    /// without knowing its type. This is what `#[serde(flatten)]`, untagged
+    /// and internally tagged enums, and dynamic values like
+    /// `serde_json::Value` need. Structs are encoded as maps keyed by field
+    /// name, tuples as seqs, and enum variants by name: a unit variant as a
+    /// string, any other as a map holding a single entry. Ignored in the C
+    /// layout, and takes precedence over the option bitmap.
+    pub fn self_describing(mut self, self_describing: bool) -> Self {
+        self.self_describing = self_describing;
+        self
+    }
+
+    /// Write map entries sorted by their encoded key, so that equal maps
+    /// always encode to the same bytes, and reject duplicate or unsorted keys
+    /// when decoding. Sets are indistinguishable from seqs, so set fields opt
+    /// in with `#[serde(with = "transform::canonical_set")]`.
+    pub fn canonical(mut self, canonical: bool) -> Self {
+        self.canonical = canonical;
+        self
+    }
+
+    // Whether structs, tuples and enum variants carry a byte-length frame
+    pub(crate) fn frames_containers(&self) -> bool {
+        self.layout == Layout::Framed
+    }
+
+    // Whether fields are padded to their natural alignment
+    pub(crate) fn is_aligned(&self) -> bool {
+        self.layout == Layout::C
+    }
+
+    pub(crate) fn uses_option_bitmap(&self) -> bool {
+        self.option_bitmap && !self.is_aligned() && !self.is_self_describing()
+    }
+
+    pub(crate) fn is_self_describing(&self) -> bool {
+        self.self_describing && !self.is_aligned()
+    }
+}
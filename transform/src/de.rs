@@ -1,41 +1,171 @@
-use crate::{Error, Result};
+use crate::align::{padding, Alignments};
+use crate::canonical_set::SET;
+use crate::hash::fnv1a;
+use crate::input::{Input, ReaderInput, SliceInput};
+use crate::presence::{bitmap_len, is_present, FieldDeserializer};
+use crate::raw::RAW;
+use crate::tag::Tag;
+use crate::{Config, Error, Result, SeqLength, VariantEncoding};
 use serde::de::{self, Deserialize};
+use std::borrow::Cow;
 use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::io;
 
-pub struct BytesDeserializer {
-    buffer: RefCell<Vec<u8>>,
-    position: RefCell<usize>,
+pub struct BytesDeserializer<I> {
+    input: RefCell<I>,
     offsets: RefCell<Vec<usize>>,
+    alignments: RefCell<Alignments>,
+    // Position the current value started at
+    base: RefCell<usize>,
+    // Bytes read while any capture is open, and the number of open captures
+    captured: RefCell<Vec<u8>>,
+    captures: RefCell<usize>,
+    // Whether the next seq is a set
+    set_next: RefCell<bool>,
+    config: Config,
 }
 
-impl BytesDeserializer {
-    pub fn new() -> Self {
+impl<'a> BytesDeserializer<SliceInput<'a>> {
+    /// Decode values one after another from `bytes`.
+    pub fn from_slice(bytes: &'a [u8], config: Config) -> Self {
+        Self::with_input(SliceInput::new(bytes), config)
+    }
+}
+
+impl<R: io::Read> BytesDeserializer<ReaderInput<R>> {
+    /// Decode values one after another from `reader`.
+    pub fn from_reader(reader: R, config: Config) -> Self {
+        Self::with_input(ReaderInput::new(reader), config)
+    }
+}
+
+impl<'de, I: Input<'de>> BytesDeserializer<I> {
+    pub(crate) fn with_input(input: I, config: Config) -> Self {
         BytesDeserializer {
-            buffer: RefCell::new(Vec::new()),
-            position: RefCell::new(0),
+            input: RefCell::new(input),
             offsets: RefCell::new(Vec::new()),
+            alignments: RefCell::new(Alignments::default()),
+            base: RefCell::new(0),
+            captured: RefCell::new(Vec::new()),
+            captures: RefCell::new(0),
+            set_next: RefCell::new(false),
+            config,
         }
     }
 
-    pub fn from_bytes<'a, T>(&self, bytes: &[u8]) -> Result<T>
+    /// Decode the next value. Any state left behind by a failed decode is
+    /// cleared, but the input it consumed is not given back.
+    pub fn deserialize<T>(&self) -> Result<T>
+    where
+        T: Deserialize<'de>,
+    {
+        *self.base.borrow_mut() = self.peek_position();
+        let result = self.deserialize_measured();
+        self.reset();
+        result
+    }
+
+    fn deserialize_measured<T>(&self) -> Result<T>
     where
-        T: Deserialize<'a>,
+        T: Deserialize<'de>,
     {
-        self.buffer.borrow_mut().clear();
-        self.buffer.borrow_mut().extend(bytes);
+        if self.config.is_aligned() {
+            // The first pass only measures the alignment of every container,
+            // walking the type without reading the input
+            T::deserialize(self)?;
+            self.alignments.borrow_mut().finish_measuring();
+        }
         T::deserialize(self)
     }
 
-    fn read_bytes(&self, len: usize) -> Result<Vec<u8>> {
-        let mut pos = self.position.borrow_mut();
-        let buffer = self.buffer.borrow();
-        let end = *pos + len;
-        if end > buffer.len() {
-            return Err(Error::Custom("Unexpected end of input".to_string()));
+    // Whether the input is exhausted, checked between values
+    pub(crate) fn at_end(&self) -> Result<bool> {
+        self.input.borrow_mut().at_end()
+    }
+
+    /// Number of bytes consumed so far.
+    pub fn position(&self) -> usize {
+        self.peek_position()
+    }
+
+    /// Clear all decoding state, including anything left behind by a failed
+    /// decode.
+    pub fn reset(&self) {
+        self.offsets.borrow_mut().clear();
+        self.alignments.take();
+        self.captured.borrow_mut().clear();
+        *self.captures.borrow_mut() = 0;
+        *self.set_next.borrow_mut() = false;
+    }
+
+    // Whether this is the measuring pass of the C layout
+    fn is_measuring(&self) -> bool {
+        self.config.is_aligned() && !self.alignments.borrow().is_measured()
+    }
+
+    // Every read from the input goes through here, so that it can be
+    // captured
+    fn take(&self, len: usize) -> Result<Cow<'de, [u8]>> {
+        let bytes = self.input.borrow_mut().read_borrowed(len)?;
+        if *self.captures.borrow() > 0 {
+            self.captured.borrow_mut().extend_from_slice(&bytes);
+        }
+        Ok(bytes)
+    }
+
+    // Start recording the bytes read, returning where the recording starts
+    fn start_capture(&self) -> usize {
+        *self.captures.borrow_mut() += 1;
+        self.captured.borrow().len()
+    }
+
+    fn end_capture(&self, start: usize) -> Vec<u8> {
+        let mut captures = self.captures.borrow_mut();
+        *captures -= 1;
+        let mut captured = self.captured.borrow_mut();
+        let bytes = captured[start..].to_vec();
+        if *captures == 0 {
+            captured.clear();
+        }
+        bytes
+    }
+
+    // Capture the exact bytes of the next value, borrowing them from the input
+    // when it allows it
+    fn deserialize_raw<V: de::Visitor<'de>>(&self, visitor: V) -> Result<V::Value> {
+        let start = self.peek_position();
+        let capture = self.start_capture();
+        let skipped = de::Deserializer::deserialize_ignored_any(self, de::IgnoredAny);
+        let captured = self.end_capture(capture);
+        skipped?;
+        let borrowed = self.input.borrow().consumed_since(start);
+        match borrowed {
+            Some(bytes) => visitor.visit_borrowed_bytes(bytes),
+            None => visitor.visit_byte_buf(captured),
         }
-        let result = buffer[*pos..end].to_vec();
-        *pos = end;
-        Ok(result)
+    }
+
+    // Decode a map key or set element, checking that it sorts after the
+    // previous one in the canonical mode
+    fn deserialize_ordered<T: de::DeserializeSeed<'de>>(
+        &self,
+        seed: T,
+        last: &mut Option<Vec<u8>>,
+    ) -> Result<T::Value> {
+        let start = self.start_capture();
+        let value = seed.deserialize(self);
+        let key = self.end_capture(start);
+        let value = value?;
+        if last.as_ref().is_some_and(|last| *last >= key) {
+            return Err(Error::Custom("keys are not in canonical order".to_string()));
+        }
+        *last = Some(key);
+        Ok(value)
+    }
+
+    fn read_bytes(&self, len: usize) -> Result<Vec<u8>> {
+        self.take(len).map(Cow::into_owned)
     }
 
     fn read_byte(&self) -> Result<u8> {
@@ -48,72 +178,452 @@ impl BytesDeserializer {
         Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
     }
 
+    // Read bytes preceded by their u32 length, borrowing them from the input
+    // when it allows it
+    fn read_length_prefixed(&self) -> Result<Cow<'de, [u8]>> {
+        self.check_not_aligned()?;
+        let len = self.read_u32()? as usize;
+        self.take(len)
+    }
+
+    // Read a primitive, skipping the padding to its natural alignment in the
+    // C layout
+    fn read_primitive<const N: usize>(&self) -> Result<[u8; N]> {
+        if self.is_measuring() {
+            self.alignments.borrow_mut().primitive(N);
+            let mut array = [0; N];
+            array[0] = 1;
+            return Ok(array);
+        }
+        if self.config.is_aligned() {
+            self.skip_padding(N)?;
+        }
+        let bytes = self.read_bytes(N)?;
+        let mut array = [0; N];
+        array.copy_from_slice(&bytes);
+        Ok(array)
+    }
+
+    fn skip_padding(&self, align: usize) -> Result<()> {
+        if self.is_measuring() {
+            return Ok(());
+        }
+        // Alignment is relative to the start of the value
+        let len = padding(self.peek_position() - *self.base.borrow(), align);
+        if self.read_bytes(len)?.iter().any(|&byte| byte != 0) {
+            return Err(Error::InvalidData);
+        }
+        Ok(())
+    }
+
+    fn deserialize_bitmap_struct<V: de::Visitor<'de>>(&self, visitor: V) -> Result<V::Value> {
+        // Absent fields take no bytes, so the struct is bounded by its field
+        // count rather than by its frame
+        let frame = if self.config.frames_containers() {
+            Some(self.read_u32()? as usize)
+        } else {
+            None
+        };
+        let start = self.peek_position();
+        // The field count comes from the input, as the fields the type
+        // declares may include aliases and skipped fields
+        let len = u16::from_le_bytes(self.read_primitive()?) as usize;
+        let bitmap = self.read_bytes(bitmap_len(len))?;
+        let value = visitor.visit_seq(SeqAccess {
+            de: self,
+            remaining: Remaining::Elements(len),
+            bitmap: Some((bitmap, 0)),
+            ordered: None,
+        })?;
+        // Fields the type doesn't read aren't skipped, so the frame has to
+        // match exactly
+        if frame.is_some_and(|frame| frame != self.peek_position() - start) {
+            return Err(Error::InvalidData);
+        }
+        Ok(value)
+    }
+
+    // Resolve the encoded variant against the variants of the enum
+    fn read_variant_index(&self, variants: &'static [&'static str]) -> Result<u32> {
+        let index = match self.config.variant_encoding {
+            VariantEncoding::Index => return Ok(self.read_byte()? as u32),
+            VariantEncoding::Name => {
+                let len = self.read_u32()? as usize;
+                let bytes = self.read_bytes(len)?;
+                let name = std::str::from_utf8(&bytes).map_err(|_| Error::InvalidData)?;
+                variants
+                    .iter()
+                    .position(|variant| *variant == name)
+                    .ok_or_else(|| <Error as de::Error>::unknown_variant(name, variants))?
+            }
+            VariantEncoding::Hash => {
+                let hash = self.read_u32()?;
+                let mut matches = variants
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, variant)| fnv1a(variant.as_bytes()) == hash)
+                    .map(|(index, _)| index);
+                let index = matches
+                    .next()
+                    .ok_or_else(|| Error::Custom(format!("unknown variant hash {:#010x}", hash)))?;
+                if matches.next().is_some() {
+                    return Err(Error::Custom(format!(
+                        "variant hash {:#010x} is ambiguous",
+                        hash
+                    )));
+                }
+                index
+            }
+        };
+        Ok(index as u32)
+    }
+
+    // Types without a C equivalent can't be decoded in the C layout
+    fn check_not_aligned(&self) -> Result<()> {
+        if self.config.is_aligned() {
+            Err(Error::Unimplemented)
+        } else {
+            Ok(())
+        }
+    }
+
     fn peek_position(&self) -> usize {
-        *self.position.borrow()
+        self.input.borrow().position()
+    }
+
+    fn deserialize_framed_seq<V: de::Visitor<'de>>(
+        &self,
+        is_struct: bool,
+        visitor: V,
+    ) -> Result<V::Value> {
+        // read u32 for number of bytes
+        let len = self.read_u32()? as usize;
+        self.visit_frame(SeqAccess::new(self, len), len, is_struct, visitor)
+    }
+
+    // Visit the elements of a frame covering `len` bytes. A struct reader
+    // that expects fewer fields than were written skips the trailing ones, and
+    // one that expects more sees the frame end early and falls back to
+    // `#[serde(default)]`. Any other reader must consume the whole frame.
+    fn visit_frame<V: de::Visitor<'de>>(
+        &self,
+        seq: SeqAccess<'_, I>,
+        len: usize,
+        is_struct: bool,
+        visitor: V,
+    ) -> Result<V::Value> {
+        let start = self.peek_position();
+        // Push the current position to the offsets
+        self.offsets.borrow_mut().push(start);
+        let value = visitor.visit_seq(seq)?;
+        self.offsets.borrow_mut().pop();
+
+        let consumed = self.peek_position() - start;
+        let trailing = len.checked_sub(consumed).ok_or(Error::InvalidData)?;
+        if trailing > 0 && !is_struct {
+            return Err(Error::InvalidData);
+        }
+        self.take(trailing)?;
+        Ok(value)
+    }
+
+    fn deserialize_seq_contents<V: de::Visitor<'de>>(
+        &self,
+        is_struct: bool,
+        visitor: V,
+    ) -> Result<V::Value> {
+        let is_set = self.set_next.take();
+        // read u32 for number of bytes or elements
+        let len = self.read_u32()? as usize;
+        match self.config.seq_length {
+            SeqLength::Bytes => {
+                let seq = SeqAccess::new(self, len).ordered(is_set);
+                self.visit_frame(seq, len, is_struct, visitor)
+            }
+            SeqLength::Elements => visitor.visit_seq(SeqAccess::counted(self, len).ordered(is_set)),
+        }
+    }
+
+    fn deserialize_map_contents<V: de::Visitor<'de>>(&self, visitor: V) -> Result<V::Value> {
+        // read u32 for number of bytes
+        let len = self.read_u32()? as usize;
+        // Push the current position to the offsets
+        self.offsets.borrow_mut().push(self.peek_position());
+        match visitor.visit_map(MapAccess::new(self, len)) {
+            Ok(value) => {
+                self.offsets.borrow_mut().pop();
+                Ok(value)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // Decode a value of the self-describing mode, whatever its type. Structs
+    // are written as seqs, which may hold more fields than the reader knows.
+    fn deserialize_described<V: de::Visitor<'de>>(
+        &self,
+        is_struct: bool,
+        visitor: V,
+    ) -> Result<V::Value> {
+        match Tag::from_byte(self.read_byte()?)? {
+            Tag::Unit => visitor.visit_unit(),
+            Tag::Bool => match self.read_byte()? {
+                0 => visitor.visit_bool(false),
+                1 => visitor.visit_bool(true),
+                _ => Err(Error::InvalidData),
+            },
+            Tag::I8 => visitor.visit_i8(i8::from_le_bytes(self.read_primitive()?)),
+            Tag::I16 => visitor.visit_i16(i16::from_le_bytes(self.read_primitive()?)),
+            Tag::I32 => visitor.visit_i32(i32::from_le_bytes(self.read_primitive()?)),
+            Tag::I64 => visitor.visit_i64(i64::from_le_bytes(self.read_primitive()?)),
+            Tag::U8 => visitor.visit_u8(self.read_byte()?),
+            Tag::U16 => visitor.visit_u16(u16::from_le_bytes(self.read_primitive()?)),
+            Tag::U32 => visitor.visit_u32(u32::from_le_bytes(self.read_primitive()?)),
+            Tag::U64 => visitor.visit_u64(u64::from_le_bytes(self.read_primitive()?)),
+            Tag::F32 => visitor.visit_f32(f32::from_le_bytes(self.read_primitive()?)),
+            Tag::F64 => visitor.visit_f64(f64::from_le_bytes(self.read_primitive()?)),
+            Tag::Char => {
+                let scalar = u32::from_le_bytes(self.read_primitive()?);
+                visitor.visit_char(char::from_u32(scalar).ok_or(Error::InvalidData)?)
+            }
+            Tag::Str => match self.read_length_prefixed()? {
+                Cow::Borrowed(bytes) => visitor.visit_borrowed_str(
+                    std::str::from_utf8(bytes).map_err(|_| Error::InvalidData)?,
+                ),
+                Cow::Owned(bytes) => {
+                    visitor.visit_string(String::from_utf8(bytes).map_err(|_| Error::InvalidData)?)
+                }
+            },
+            Tag::Bytes => match self.read_length_prefixed()? {
+                Cow::Borrowed(bytes) => visitor.visit_borrowed_bytes(bytes),
+                Cow::Owned(bytes) => visitor.visit_byte_buf(bytes),
+            },
+            Tag::None => visitor.visit_none(),
+            Tag::Some => visitor.visit_some(self),
+            Tag::Seq => self.deserialize_seq_contents(is_struct, visitor),
+            Tag::Map => self.deserialize_map_contents(visitor),
+        }
+    }
+
+    // Structs, tuples and tuple structs are framed by byte length in the
+    // framed layout, otherwise the field count from the type bounds them
+    fn deserialize_container<V: de::Visitor<'de>>(
+        &self,
+        len: usize,
+        is_struct: bool,
+        visitor: V,
+    ) -> Result<V::Value> {
+        if self.config.frames_containers() {
+            self.deserialize_framed_seq(is_struct, visitor)
+        } else if self.config.is_aligned() {
+            let align = self.alignments.borrow_mut().open();
+            self.skip_padding(align)?;
+            let value = visitor.visit_seq(SeqAccess::counted(self, len))?;
+            // Trailing padding rounds the size up to the container alignment
+            let align = self.alignments.borrow_mut().close();
+            self.skip_padding(align)?;
+            Ok(value)
+        } else {
+            visitor.visit_seq(SeqAccess::counted(self, len))
+        }
     }
 }
 
-pub fn from_bytes<'a, T>(bytes: &[u8]) -> Result<T>
+pub fn from_bytes<'a, T>(bytes: &'a [u8]) -> Result<T>
 where
     T: de::Deserialize<'a>,
 {
-    let de = BytesDeserializer::new();
-    de.from_bytes(bytes)
+    from_bytes_with_config(bytes, Config::default())
 }
 
-impl<'de> de::Deserializer<'de> for &BytesDeserializer {
-    type Error = Error;
+pub fn from_bytes_with_config<'a, T>(bytes: &'a [u8], config: Config) -> Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    let de = BytesDeserializer::with_input(SliceInput::new(bytes), config);
+    de.deserialize()
+}
 
-    fn deserialize_any<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+// Size of the header in front of a top-level frame: its u32 byte length,
+// after the type tag in the self-describing mode. Without frames around
+// structs and tuples, or with element-counted seqs, a top-level value may
+// not start with a byte length at all.
+pub(crate) fn frame_header_len(config: Config) -> Result<usize> {
+    if config.seq_length != SeqLength::Bytes {
+        return Err(Error::Unimplemented);
+    }
+    if config.is_self_describing() {
+        Ok(5)
+    } else if config.frames_containers() {
+        Ok(4)
+    } else {
         Err(Error::Unimplemented)
     }
+}
 
-    fn deserialize_bool<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
-        Err(Error::Unimplemented)
+// Size of the frame at the start of `bytes`, header included, or `None` while
+// its header is incomplete
+pub(crate) fn frame_len(bytes: &[u8], config: Config) -> Result<Option<usize>> {
+    let header_len = frame_header_len(config)?;
+    let Some(header) = bytes.get(..header_len) else {
+        return Ok(None);
+    };
+    if config.is_self_describing() {
+        // Only these tags are followed by a byte length
+        match Tag::from_byte(header[0])? {
+            Tag::Str | Tag::Bytes | Tag::Seq | Tag::Map => {}
+            _ => return Err(Error::Unimplemented),
+        }
     }
+    let len = &header[header_len - 4..];
+    let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+    len.checked_add(header_len)
+        .map(Some)
+        .ok_or(Error::InvalidData)
+}
 
-    fn deserialize_i8<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
-        Err(Error::Unimplemented)
+// Decode the framed value that `bytes` holds, checking that the frame covers
+// all of `bytes`
+pub(crate) fn decode_framed<'a, T>(bytes: &'a [u8], config: Config) -> Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    let total = frame_len(bytes, config)?.ok_or(Error::UnexpectedEof)?;
+    match total.cmp(&bytes.len()) {
+        Ordering::Greater => Err(Error::UnexpectedEof),
+        Ordering::Less => Err(Error::InvalidData),
+        Ordering::Equal => decode_exact(bytes, config),
     }
+}
 
-    fn deserialize_i16<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
-        Err(Error::Unimplemented)
+// Decode a value that must take up all of `bytes`
+pub(crate) fn decode_exact<'a, T>(bytes: &'a [u8], config: Config) -> Result<T>
+where
+    T: de::Deserialize<'a>,
+{
+    let de = BytesDeserializer::from_slice(bytes, config);
+    let value = de.deserialize()?;
+    if de.position() != bytes.len() {
+        return Err(Error::InvalidData);
     }
+    Ok(value)
+}
 
-    fn deserialize_i32<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
-        Err(Error::Unimplemented)
+pub fn from_reader<R, T>(reader: R) -> Result<T>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    from_reader_with_config(reader, Config::default())
+}
+
+pub fn from_reader_with_config<R, T>(reader: R, config: Config) -> Result<T>
+where
+    R: io::Read,
+    T: de::DeserializeOwned,
+{
+    let de = BytesDeserializer::with_input(ReaderInput::new(reader), config);
+    de.deserialize()
+}
+
+impl<'de, I: Input<'de>> de::Deserializer<'de> for &BytesDeserializer<I> {
+    type Error = Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.config.is_self_describing() {
+            self.deserialize_described(false, visitor)
+        } else {
+            Err(Error::Unimplemented)
+        }
     }
 
-    fn deserialize_i64<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
-        Err(Error::Unimplemented)
+    fn deserialize_bool<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        match self.read_primitive::<1>()? {
+            [0] => visitor.visit_bool(false),
+            [1] => visitor.visit_bool(true),
+            _ => Err(Error::InvalidData),
+        }
+    }
+
+    fn deserialize_i8<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        visitor.visit_i8(i8::from_le_bytes(self.read_primitive()?))
+    }
+
+    fn deserialize_i16<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        visitor.visit_i16(i16::from_le_bytes(self.read_primitive()?))
+    }
+
+    fn deserialize_i32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        visitor.visit_i32(i32::from_le_bytes(self.read_primitive()?))
+    }
+
+    fn deserialize_i64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        visitor.visit_i64(i64::from_le_bytes(self.read_primitive()?))
     }
 
     fn deserialize_u8<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_u8(self.read_byte()?)
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        visitor.visit_u8(u8::from_le_bytes(self.read_primitive()?))
     }
 
-    fn deserialize_u16<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
-        Err(Error::Unimplemented)
+    fn deserialize_u16<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        visitor.visit_u16(u16::from_le_bytes(self.read_primitive()?))
     }
 
     fn deserialize_u32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_u32(self.read_u32()?)
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        visitor.visit_u32(u32::from_le_bytes(self.read_primitive()?))
     }
 
-    fn deserialize_u64<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
-        Err(Error::Unimplemented)
+    fn deserialize_u64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        visitor.visit_u64(u64::from_le_bytes(self.read_primitive()?))
     }
 
-    fn deserialize_f32<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
-        Err(Error::Unimplemented)
+    fn deserialize_f32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        visitor.visit_f32(f32::from_le_bytes(self.read_primitive()?))
     }
 
-    fn deserialize_f64<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
-        Err(Error::Unimplemented)
+    fn deserialize_f64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        visitor.visit_f64(f64::from_le_bytes(self.read_primitive()?))
     }
 
-    fn deserialize_char<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
-        Err(Error::Unimplemented)
+    fn deserialize_char<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        let scalar = u32::from_le_bytes(self.read_primitive()?);
+        visitor.visit_char(char::from_u32(scalar).ok_or(Error::InvalidData)?)
     }
 
     /// Hint that the `Deserialize` type is expecting a string value and does
@@ -123,8 +633,17 @@ impl<'de> de::Deserializer<'de> for &BytesDeserializer {
     /// If the `Visitor` would benefit from taking ownership of `String` data,
     /// indicate this to the `Deserializer` by using `deserialize_string`
     /// instead.
-    fn deserialize_str<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
-        Err(Error::Unimplemented)
+    fn deserialize_str<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        match self.read_length_prefixed()? {
+            Cow::Borrowed(bytes) => visitor
+                .visit_borrowed_str(std::str::from_utf8(bytes).map_err(|_| Error::InvalidData)?),
+            Cow::Owned(bytes) => {
+                visitor.visit_str(std::str::from_utf8(&bytes).map_err(|_| Error::InvalidData)?)
+            }
+        }
     }
 
     /// Hint that the `Deserialize` type is expecting a string value and would
@@ -134,8 +653,17 @@ impl<'de> de::Deserializer<'de> for &BytesDeserializer {
     /// If the `Visitor` would not benefit from taking ownership of `String`
     /// data, indicate that to the `Deserializer` by using `deserialize_str`
     /// instead.
-    fn deserialize_string<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
-        Err(Error::Unimplemented)
+    fn deserialize_string<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        match self.read_length_prefixed()? {
+            Cow::Borrowed(bytes) => visitor
+                .visit_borrowed_str(std::str::from_utf8(bytes).map_err(|_| Error::InvalidData)?),
+            Cow::Owned(bytes) => {
+                visitor.visit_string(String::from_utf8(bytes).map_err(|_| Error::InvalidData)?)
+            }
+        }
     }
 
     /// Hint that the `Deserialize` type is expecting a byte array and does not
@@ -145,9 +673,14 @@ impl<'de> de::Deserializer<'de> for &BytesDeserializer {
     /// If the `Visitor` would benefit from taking ownership of `Vec<u8>` data,
     /// indicate this to the `Deserializer` by using `deserialize_byte_buf`
     /// instead.
-    fn deserialize_bytes<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
-        // Unimplmented for now as serialization is without length prefix
-        Err(Error::Unimplemented)
+    fn deserialize_bytes<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        match self.read_length_prefixed()? {
+            Cow::Borrowed(bytes) => visitor.visit_borrowed_bytes(bytes),
+            Cow::Owned(bytes) => visitor.visit_bytes(&bytes),
+        }
     }
 
     /// Hint that the `Deserialize` type is expecting a byte array and would
@@ -157,8 +690,14 @@ impl<'de> de::Deserializer<'de> for &BytesDeserializer {
     /// If the `Visitor` would not benefit from taking ownership of `Vec<u8>`
     /// data, indicate that to the `Deserializer` by using `deserialize_bytes`
     /// instead.
-    fn deserialize_byte_buf<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
-        Err(Error::Unimplemented)
+    fn deserialize_byte_buf<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        match self.read_length_prefixed()? {
+            Cow::Borrowed(bytes) => visitor.visit_borrowed_bytes(bytes),
+            Cow::Owned(bytes) => visitor.visit_byte_buf(bytes),
+        }
     }
 
     /// Hint that the `Deserialize` type is expecting an optional value.
@@ -167,6 +706,10 @@ impl<'de> de::Deserializer<'de> for &BytesDeserializer {
     /// value to convert the null value into `None` and a regular value into
     /// `Some(value)`.
     fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        self.check_not_aligned()?;
         let byte = self.read_byte()?;
         match byte {
             0 => de::Visitor::visit_none(visitor),
@@ -177,7 +720,14 @@ impl<'de> de::Deserializer<'de> for &BytesDeserializer {
 
     /// Hint that the `Deserialize` type is expecting a unit value.
     fn deserialize_unit<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        let byte = self.read_byte()?;
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        // Units are zero-sized in C
+        if self.config.is_aligned() {
+            return visitor.visit_unit();
+        }
+        let [byte] = self.read_primitive()?;
         if byte == 0 {
             visitor.visit_unit()
         } else {
@@ -192,7 +742,14 @@ impl<'de> de::Deserializer<'de> for &BytesDeserializer {
         _name: &'static str,
         visitor: V,
     ) -> Result<V::Value> {
-        let byte = self.read_byte()?;
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        // Units are zero-sized in C
+        if self.config.is_aligned() {
+            return visitor.visit_unit();
+        }
+        let [byte] = self.read_primitive()?;
         if byte == 0 {
             visitor.visit_unit()
         } else {
@@ -204,33 +761,36 @@ impl<'de> de::Deserializer<'de> for &BytesDeserializer {
     /// particular name.
     fn deserialize_newtype_struct<V: de::Visitor<'de>>(
         self,
-        _name: &'static str,
+        name: &'static str,
         visitor: V,
     ) -> Result<V::Value> {
+        if self.config.canonical && name == SET {
+            *self.set_next.borrow_mut() = true;
+        }
+        if name == RAW {
+            return self.deserialize_raw(visitor);
+        }
         visitor.visit_newtype_struct(self)
     }
 
     /// Hint that the `Deserialize` type is expecting a sequence of values.
     /// We need to implement this
     fn deserialize_seq<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        // read u32 for number of bytes
-        let len = self.read_u32()? as usize;
-        // Push the current buffer length to the offsets
-        self.offsets.borrow_mut().push(self.buffer.borrow().len());
-        match visitor.visit_seq(SeqAccess::new(self, len)) {
-            Ok(value) => {
-                self.offsets.borrow_mut().pop();
-                Ok(value)
-            }
-            Err(e) => Err(e),
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
         }
+        self.check_not_aligned()?;
+        self.deserialize_seq_contents(false, visitor)
     }
 
     /// Hint that the `Deserialize` type is expecting a sequence of values and
     /// knows how many values there are without looking at the serialized data.
     /// We need to implement this
-    fn deserialize_tuple<V: de::Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
-        self.deserialize_seq(visitor)
+    fn deserialize_tuple<V: de::Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        self.deserialize_container(len, false, visitor)
     }
 
     /// Hint that the `Deserialize` type is expecting a tuple struct with a
@@ -238,25 +798,22 @@ impl<'de> de::Deserializer<'de> for &BytesDeserializer {
     fn deserialize_tuple_struct<V: de::Visitor<'de>>(
         self,
         _name: &'static str,
-        _len: usize,
+        len: usize,
         visitor: V,
     ) -> Result<V::Value> {
-        self.deserialize_seq(visitor)
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        self.deserialize_container(len, false, visitor)
     }
 
     /// Hint that the `Deserialize` type is expecting a map of key-value pairs.
     fn deserialize_map<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        // read u32 for number of bytes
-        let len = self.read_u32()? as usize;
-        // Push the current buffer length to the offsets
-        self.offsets.borrow_mut().push(self.buffer.borrow().len());
-        match visitor.visit_map(MapAccess::new(self, len)) {
-            Ok(value) => {
-                self.offsets.borrow_mut().pop();
-                Ok(value)
-            }
-            Err(e) => Err(e),
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
         }
+        self.check_not_aligned()?;
+        self.deserialize_map_contents(visitor)
     }
 
     /// Hint that the `Deserialize` type is expecting a struct with a particular
@@ -265,10 +822,17 @@ impl<'de> de::Deserializer<'de> for &BytesDeserializer {
     fn deserialize_struct<V: de::Visitor<'de>>(
         self,
         _name: &'static str,
-        _fields: &'static [&'static str],
+        fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value> {
-        self.deserialize_seq(visitor)
+        if self.config.is_self_describing() {
+            return self.deserialize_described(true, visitor);
+        }
+        if self.config.uses_option_bitmap() {
+            self.deserialize_bitmap_struct(visitor)
+        } else {
+            self.deserialize_container(fields.len(), true, visitor)
+        }
     }
 
     /// Hint that the `Deserialize` type is expecting an enum value with a
@@ -276,69 +840,169 @@ impl<'de> de::Deserializer<'de> for &BytesDeserializer {
     fn deserialize_enum<V: de::Visitor<'de>>(
         self,
         _name: &'static str,
-        _variants: &'static [&'static str],
+        variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value> {
-        let remaining = self.read_u32()? as usize;
-        let variant_index = self.read_byte()?;
+        self.check_not_aligned()?;
+        if self.config.is_self_describing() {
+            return match Tag::from_byte(self.read_byte()?)? {
+                Tag::Str => {
+                    let name = self.read_length_prefixed()?;
+                    let name = std::str::from_utf8(&name).map_err(|_| Error::InvalidData)?;
+                    visitor.visit_enum(de::value::StrDeserializer::<Error>::new(name))
+                }
+                Tag::Map => {
+                    let len = self.read_u32()? as usize;
+                    let start = self.peek_position();
+                    let value = visitor.visit_enum(DescribedEnumAccess { de: self })?;
+                    // The map holds the variant as its only entry
+                    if self.peek_position() - start != len {
+                        return Err(Error::InvalidData);
+                    }
+                    Ok(value)
+                }
+                _ => Err(Error::InvalidData),
+            };
+        }
+        let remaining = if self.config.frames_containers() {
+            self.read_u32()? as usize
+        } else {
+            0
+        };
+        let before = self.peek_position();
+        let variant_index = self.read_variant_index(variants)?;
+        // The frame also covers the variant, which has been consumed by now
+        let remaining = remaining.saturating_sub(self.peek_position() - before);
 
         visitor.visit_enum(EnumAccess::new(self, variant_index, remaining))
     }
 
     /// Hint that the `Deserialize` type is expecting the name of a struct
     /// field or the discriminant of an enum variant.
-    fn deserialize_identifier<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+    fn deserialize_identifier<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
         Err(Error::Unimplemented)
     }
 
     /// Hint that the `Deserialize` type needs to deserialize a value whose type
     /// doesn't matter because it is ignored.
     ///
-    /// Deserializers for non-self-describing formats may not support this mode.
-    fn deserialize_ignored_any<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
-        Err(Error::Unimplemented)
+    /// The value is skipped by its leading u32 length, so it must be one that
+    /// starts with one: a struct, tuple, enum or map in the framed layout, a
+    /// seq with a byte-length prefix, or a string or byte array. Bools,
+    /// numbers, chars, options and units carry no byte length and can't be
+    /// skipped. Outside the framed layout, or with element-counted seqs, a
+    /// leading u32 may not be a byte length at all, so skipping fails with
+    /// `Error::Unimplemented`. In the self-describing mode, any value can be
+    /// skipped.
+    fn deserialize_ignored_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.config.is_self_describing() {
+            return self.deserialize_any(visitor);
+        }
+        if !self.config.frames_containers() || self.config.seq_length == SeqLength::Elements {
+            return Err(Error::Unimplemented);
+        }
+        self.read_length_prefixed()?;
+        visitor.visit_unit()
     }
 }
 
-struct SeqAccess<'a> {
-    de: &'a BytesDeserializer,
-    remaining: usize,
+// A sequence is bounded either by the bytes left in its frame or, for
+// unframed containers, by the number of elements left
+enum Remaining {
+    Bytes(usize),
+    Elements(usize),
+}
+
+struct SeqAccess<'a, I> {
+    de: &'a BytesDeserializer<I>,
+    remaining: Remaining,
+    // Presence bitmap and next field index in the option bitmap mode
+    bitmap: Option<(Vec<u8>, usize)>,
+    // For a set in the canonical mode, the encoding of the previous element
+    ordered: Option<Option<Vec<u8>>>,
 }
 
-impl<'a> SeqAccess<'a> {
-    fn new(de: &'a BytesDeserializer, remaining: usize) -> Self {
-        SeqAccess { de, remaining }
+impl<'a, I> SeqAccess<'a, I> {
+    fn new(de: &'a BytesDeserializer<I>, remaining: usize) -> Self {
+        SeqAccess {
+            de,
+            remaining: Remaining::Bytes(remaining),
+            bitmap: None,
+            ordered: None,
+        }
+    }
+
+    fn counted(de: &'a BytesDeserializer<I>, len: usize) -> Self {
+        SeqAccess {
+            de,
+            remaining: Remaining::Elements(len),
+            bitmap: None,
+            ordered: None,
+        }
+    }
+
+    // Check that the elements are sorted by their encoding
+    fn ordered(mut self, ordered: bool) -> Self {
+        self.ordered = ordered.then_some(None);
+        self
     }
 }
 
-impl<'de> de::SeqAccess<'de> for SeqAccess<'_> {
+impl<'de, I: Input<'de>> de::SeqAccess<'de> for SeqAccess<'_, I> {
     type Error = Error;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
     where
         T: de::DeserializeSeed<'de>,
     {
-        if self.remaining == 0 {
-            return Ok(None);
+        match self.remaining {
+            Remaining::Bytes(0) | Remaining::Elements(0) => return Ok(None),
+            _ => {}
         }
 
         let before = self.de.peek_position();
-        let val = seed.deserialize(&*self.de)?;
+        let val = match (&mut self.bitmap, &mut self.ordered) {
+            (_, Some(last)) => self.de.deserialize_ordered(seed, last)?,
+            (Some((bitmap, index)), None) => {
+                let present = is_present(bitmap, *index);
+                *index += 1;
+                seed.deserialize(FieldDeserializer {
+                    de: self.de,
+                    present,
+                })?
+            }
+            (None, None) => seed.deserialize(self.de)?,
+        };
         let consumed = self.de.peek_position() - before;
 
-        self.remaining -= consumed;
+        match &mut self.remaining {
+            Remaining::Bytes(remaining) => {
+                *remaining = remaining.checked_sub(consumed).ok_or(Error::InvalidData)?
+            }
+            Remaining::Elements(remaining) => *remaining -= 1,
+        }
         Ok(Some(val))
     }
+
+    fn size_hint(&self) -> Option<usize> {
+        match self.remaining {
+            Remaining::Bytes(_) => None,
+            Remaining::Elements(remaining) => Some(remaining),
+        }
+    }
 }
 
-struct EnumAccess<'a> {
-    de: &'a BytesDeserializer,
-    variant_index: u8,
+struct EnumAccess<'a, I> {
+    de: &'a BytesDeserializer<I>,
+    variant_index: u32,
     remaining: RefCell<usize>,
 }
 
-impl<'a> EnumAccess<'a> {
-    fn new(de: &'a BytesDeserializer, variant_index: u8, remaining: usize) -> Self {
+impl<'a, I> EnumAccess<'a, I> {
+    fn new(de: &'a BytesDeserializer<I>, variant_index: u32, remaining: usize) -> Self {
         EnumAccess {
             de,
             variant_index,
@@ -347,7 +1011,7 @@ impl<'a> EnumAccess<'a> {
     }
 }
 
-impl<'de> de::EnumAccess<'de> for EnumAccess<'_> {
+impl<'de, I: Input<'de>> de::EnumAccess<'de> for EnumAccess<'_, I> {
     type Error = Error;
     type Variant = Self;
 
@@ -355,12 +1019,12 @@ impl<'de> de::EnumAccess<'de> for EnumAccess<'_> {
     where
         V: de::DeserializeSeed<'de>,
     {
-        let val = seed.deserialize(de::value::U8Deserializer::<Error>::new(self.variant_index))?;
+        let val = seed.deserialize(de::value::U32Deserializer::<Error>::new(self.variant_index))?;
         Ok((val, self))
     }
 }
 
-impl<'de> de::VariantAccess<'de> for EnumAccess<'_> {
+impl<'de, I: Input<'de>> de::VariantAccess<'de> for EnumAccess<'_, I> {
     type Error = Error;
 
     fn unit_variant(self) -> Result<()> {
@@ -374,57 +1038,49 @@ impl<'de> de::VariantAccess<'de> for EnumAccess<'_> {
         seed.deserialize(self.de)
     }
 
-    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
+        if !self.de.config.frames_containers() {
+            return visitor.visit_seq(SeqAccess::counted(self.de, len));
+        }
         let len = *self.remaining.borrow();
-        // Push the current buffer length to the offsets
         self.de
-            .offsets
-            .borrow_mut()
-            .push(self.de.buffer.borrow().len());
-        match visitor.visit_seq(SeqAccess::new(self.de, len)) {
-            Ok(value) => {
-                self.de.offsets.borrow_mut().pop();
-                Ok(value)
-            }
-            Err(e) => Err(e),
-        }
+            .visit_frame(SeqAccess::new(self.de, len), len, false, visitor)
     }
 
-    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
+        if !self.de.config.frames_containers() {
+            return visitor.visit_seq(SeqAccess::counted(self.de, fields.len()));
+        }
         let len = *self.remaining.borrow();
-        // Push the current buffer length to the offsets
         self.de
-            .offsets
-            .borrow_mut()
-            .push(self.de.buffer.borrow().len());
-        match visitor.visit_seq(SeqAccess::new(self.de, len)) {
-            Ok(value) => {
-                self.de.offsets.borrow_mut().pop();
-                Ok(value)
-            }
-            Err(e) => Err(e),
-        }
+            .visit_frame(SeqAccess::new(self.de, len), len, true, visitor)
     }
 }
 
-struct MapAccess<'a> {
-    de: &'a BytesDeserializer,
+struct MapAccess<'a, I> {
+    de: &'a BytesDeserializer<I>,
     remaining: usize,
+    // Encoding of the previous key in the canonical mode
+    last: Option<Vec<u8>>,
 }
 
-impl<'a> MapAccess<'a> {
-    fn new(de: &'a BytesDeserializer, len: usize) -> Self {
-        MapAccess { de, remaining: len }
+impl<'a, I> MapAccess<'a, I> {
+    fn new(de: &'a BytesDeserializer<I>, len: usize) -> Self {
+        MapAccess {
+            de,
+            remaining: len,
+            last: None,
+        }
     }
 }
 
-impl<'de> de::MapAccess<'de> for MapAccess<'_> {
+impl<'de, I: Input<'de>> de::MapAccess<'de> for MapAccess<'_, I> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
@@ -436,10 +1092,17 @@ impl<'de> de::MapAccess<'de> for MapAccess<'_> {
         }
 
         let before = self.de.peek_position();
-        let val = seed.deserialize(&*self.de)?;
+        let val = if self.de.config.canonical {
+            self.de.deserialize_ordered(seed, &mut self.last)?
+        } else {
+            seed.deserialize(self.de)?
+        };
         let consumed = self.de.peek_position() - before;
 
-        self.remaining -= consumed;
+        self.remaining = self
+            .remaining
+            .checked_sub(consumed)
+            .ok_or(Error::InvalidData)?;
         Ok(Some(val))
     }
 
@@ -451,7 +1114,58 @@ impl<'de> de::MapAccess<'de> for MapAccess<'_> {
         let val = seed.deserialize(self.de)?;
         let consumed = self.de.peek_position() - before;
 
-        self.remaining -= consumed;
+        self.remaining = self
+            .remaining
+            .checked_sub(consumed)
+            .ok_or(Error::InvalidData)?;
         Ok(val)
     }
 }
+
+// An enum variant with content in the self-describing mode, encoded as a map
+// holding a single entry keyed by the variant name
+struct DescribedEnumAccess<'a, I> {
+    de: &'a BytesDeserializer<I>,
+}
+
+impl<'de, I: Input<'de>> de::EnumAccess<'de> for DescribedEnumAccess<'_, I> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let val = seed.deserialize(self.de)?;
+        Ok((val, self))
+    }
+}
+
+impl<'de, I: Input<'de>> de::VariantAccess<'de> for DescribedEnumAccess<'_, I> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        de::Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_any(self.de, visitor)
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_any(self.de, visitor)
+    }
+}
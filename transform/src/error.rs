@@ -5,6 +5,10 @@ pub enum Error {
     Custom(String),
     Unimplemented,
     InvalidData,
+    BufferFull,
+    UnexpectedEof,
+    FrameTooLarge(usize),
+    Io(std::io::Error),
 }
 
 impl fmt::Display for Error {
@@ -13,6 +17,10 @@ impl fmt::Display for Error {
             Error::Custom(msg) => write!(f, "{}", msg),
             Error::Unimplemented => write!(f, "Unsupported type"),
             Error::InvalidData => write!(f, "Invalid data"),
+            Error::BufferFull => write!(f, "Buffer is full"),
+            Error::UnexpectedEof => write!(f, "Unexpected end of input"),
+            Error::FrameTooLarge(len) => write!(f, "Frame of {} bytes is too large", len),
+            Error::Io(err) => write!(f, "I/O error: {}", err),
         }
     }
 }
@@ -25,6 +33,12 @@ impl From<String> for Error {
     }
 }
 
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
 impl serde::de::Error for Error {
     fn custom<T: fmt::Display>(msg: T) -> Self {
         Error::Custom(msg.to_string())
@@ -0,0 +1,117 @@
+use crate::hash::{fnv1a, fnv1a_extend};
+
+/// A type whose encoding has the same size for every value, in the default
+/// configuration.
+///
+/// Types whose size depends on their value, like `Vec` or `Option`, don't
+/// implement it.
+pub trait FixedSize {
+    /// The size of the encoding in bytes.
+    const SIZE: usize;
+    /// A hash of what the encoding holds, so that types of the same size can
+    /// be told apart: the kind of a primitive, or the `BinLayout::FINGERPRINT`
+    /// of a struct. It must not change between builds.
+    const DESCRIPTOR: u32;
+}
+
+/// Where a field sits in the encoding of a struct.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FieldLayout {
+    /// The field name, or its index for tuple structs.
+    pub name: &'static str,
+    /// The offset from the start of the encoding, frame included.
+    pub offset: usize,
+    pub size: usize,
+    /// The `FixedSize::DESCRIPTOR` of the field type.
+    pub descriptor: u32,
+}
+
+/// A fixed-size struct whose fields sit at fixed offsets in its encoding, so
+/// that one can be read with `load(&bytes[field.offset..])`.
+///
+/// Implemented with `#[derive(BinLayout)]` when the `derive` feature is on.
+pub trait BinLayout: FixedSize {
+    /// The fields in declaration order.
+    const FIELDS: &'static [FieldLayout];
+    /// A hash of the size and of the name, size and type descriptor of every
+    /// field. Tables store it, so it depends on nothing else, and a type can
+    /// be renamed or moved without invalidating them.
+    const FINGERPRINT: u32 = fingerprint(Self::SIZE, Self::FIELDS);
+}
+
+const fn fingerprint(size: usize, fields: &[FieldLayout]) -> u32 {
+    let mut hash = fnv1a(&(size as u32).to_le_bytes());
+    let mut index = 0;
+    while index < fields.len() {
+        let field = &fields[index];
+        hash = fnv1a_extend(hash, &(field.name.len() as u32).to_le_bytes());
+        hash = fnv1a_extend(hash, field.name.as_bytes());
+        hash = fnv1a_extend(hash, &(field.size as u32).to_le_bytes());
+        hash = fnv1a_extend(hash, &field.descriptor.to_le_bytes());
+        index += 1;
+    }
+    hash
+}
+
+// The u32 byte-length frame of tuples and arrays
+const FRAME: usize = 4;
+
+macro_rules! impl_primitive {
+    ($($ty:ty),*) => {
+        $(
+            impl FixedSize for $ty {
+                const SIZE: usize = std::mem::size_of::<$ty>();
+                const DESCRIPTOR: u32 = fnv1a(stringify!($ty).as_bytes());
+            }
+        )*
+    };
+}
+
+impl_primitive!(bool, u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+// A char is encoded as its u32 scalar value
+impl FixedSize for char {
+    const SIZE: usize = 4;
+    const DESCRIPTOR: u32 = fnv1a(b"char");
+}
+
+// The unit is encoded as a single zero byte
+impl FixedSize for () {
+    const SIZE: usize = 1;
+    const DESCRIPTOR: u32 = fnv1a(b"()");
+}
+
+// Arrays are encoded as framed tuples
+impl<T: FixedSize, const N: usize> FixedSize for [T; N] {
+    const SIZE: usize = FRAME + N * T::SIZE;
+    const DESCRIPTOR: u32 = {
+        let hash = fnv1a_extend(fnv1a(b"array"), &(N as u32).to_le_bytes());
+        fnv1a_extend(hash, &T::DESCRIPTOR.to_le_bytes())
+    };
+}
+
+macro_rules! impl_tuple {
+    ($($name:ident)+) => {
+        impl<$($name: FixedSize),+> FixedSize for ($($name,)+) {
+            const SIZE: usize = FRAME $(+ $name::SIZE)+;
+            const DESCRIPTOR: u32 = {
+                let hash = fnv1a(b"tuple");
+                $(let hash = fnv1a_extend(hash, &$name::DESCRIPTOR.to_le_bytes());)+
+                hash
+            };
+        }
+    };
+}
+
+impl_tuple!(A);
+impl_tuple!(A B);
+impl_tuple!(A B C);
+impl_tuple!(A B C D);
+impl_tuple!(A B C D E);
+impl_tuple!(A B C D E F);
+impl_tuple!(A B C D E F G);
+impl_tuple!(A B C D E F G H);
+impl_tuple!(A B C D E F G H I);
+impl_tuple!(A B C D E F G H I J);
+impl_tuple!(A B C D E F G H I J K);
+impl_tuple!(A B C D E F G H I J K L);
@@ -0,0 +1,17 @@
+const OFFSET: u32 = 0x811c_9dc5;
+
+// 32-bit FNV-1a, used wherever a hash has to stay stable across builds
+pub(crate) const fn fnv1a(bytes: &[u8]) -> u32 {
+    fnv1a_extend(OFFSET, bytes)
+}
+
+// Continue an FNV-1a hash with more bytes. Written as a loop so that layout
+// fingerprints can be computed in constants.
+pub(crate) const fn fnv1a_extend(mut hash: u32, bytes: &[u8]) -> u32 {
+    let mut index = 0;
+    while index < bytes.len() {
+        hash = (hash ^ bytes[index] as u32).wrapping_mul(0x0100_0193);
+        index += 1;
+    }
+    hash
+}
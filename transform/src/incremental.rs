@@ -0,0 +1,71 @@
+use crate::de::{decode_framed, frame_header_len, frame_len};
+use crate::{Config, Result};
+use serde::de::DeserializeOwned;
+
+/// Outcome of an attempt to decode a value from partial input.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Progress<T> {
+    /// At least this many more bytes are needed before the value can be
+    /// decoded.
+    NeedMore(usize),
+    Done(T),
+}
+
+/// Decodes values from input that arrives in pieces, such as a non-blocking
+/// socket.
+///
+/// The outer byte-length frame tells how much input a value needs, so the
+/// top-level value must be framed: a struct, tuple, enum, map or seq in the
+/// framed layout with byte-length seqs. In the self-describing mode the frame
+/// follows the type tag. Decoding in any other configuration fails with
+/// `Error::Unimplemented`.
+pub struct IncrementalDecoder {
+    buffer: Vec<u8>,
+    config: Config,
+}
+
+impl IncrementalDecoder {
+    pub fn new() -> Self {
+        Self::with_config(Config::default())
+    }
+
+    pub fn with_config(config: Config) -> Self {
+        IncrementalDecoder {
+            buffer: Vec::new(),
+            config,
+        }
+    }
+
+    /// Buffer more input.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Number of bytes buffered and not yet decoded.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Decode the next value once all of its bytes have been fed. The bytes
+    /// of a decoded value are dropped from the buffer, even if decoding it
+    /// failed.
+    pub fn decode<T: DeserializeOwned>(&mut self) -> Result<Progress<T>> {
+        let Some(total) = frame_len(&self.buffer, self.config)? else {
+            let header_len = frame_header_len(self.config)?;
+            return Ok(Progress::NeedMore(header_len - self.buffer.len()));
+        };
+        if self.buffer.len() < total {
+            return Ok(Progress::NeedMore(total - self.buffer.len()));
+        }
+
+        let result = decode_framed(&self.buffer[..total], self.config);
+        self.buffer.drain(..total);
+        result.map(Progress::Done)
+    }
+}
+
+impl Default for IncrementalDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{Error, Result};
+use std::borrow::Cow;
+use std::io::{self, Read};
+
+/// Source of the bytes consumed by a `BytesDeserializer`. Inputs that hold
+/// all of their bytes for `'de` can lend them out instead of copying.
+pub trait Input<'de> {
+    /// Number of bytes consumed so far.
+    fn position(&self) -> usize;
+
+    /// Read exactly `len` bytes.
+    fn read(&mut self, len: usize) -> Result<Vec<u8>>;
+
+    /// Read bytes, borrowing them from the input when it allows it.
+    fn read_borrowed(&mut self, len: usize) -> Result<Cow<'de, [u8]>> {
+        self.read(len).map(Cow::Owned)
+    }
+
+    /// Whether the input is exhausted.
+    fn at_end(&mut self) -> Result<bool>;
+
+    /// The bytes consumed since `start`, when the input can lend them.
+    fn consumed_since(&self, _start: usize) -> Option<&'de [u8]> {
+        None
+    }
+}
+
+/// Input read from a byte slice, which strings and bytes can borrow from.
+pub struct SliceInput<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> SliceInput<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        SliceInput { bytes, position: 0 }
+    }
+}
+
+impl<'a> Input<'a> for SliceInput<'a> {
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn read(&mut self, len: usize) -> Result<Vec<u8>> {
+        self.read_borrowed(len).map(Cow::into_owned)
+    }
+
+    fn read_borrowed(&mut self, len: usize) -> Result<Cow<'a, [u8]>> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(Error::UnexpectedEof)?;
+        let bytes: &'a [u8] = self.bytes;
+        self.position = end;
+        Ok(Cow::Borrowed(&bytes[end - len..end]))
+    }
+
+    fn at_end(&mut self) -> Result<bool> {
+        Ok(self.position == self.bytes.len())
+    }
+
+    fn consumed_since(&self, start: usize) -> Option<&'a [u8]> {
+        self.bytes.get(start..self.position)
+    }
+}
+
+/// Input pulled from a reader, exactly the bytes each value needs. There is
+/// no internal buffering, so small reads go straight to the reader.
+pub struct ReaderInput<R> {
+    reader: R,
+    position: usize,
+    // Byte read ahead to check for the end of the input
+    peeked: Option<u8>,
+}
+
+impl<R: Read> ReaderInput<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        ReaderInput {
+            reader,
+            position: 0,
+            peeked: None,
+        }
+    }
+}
+
+impl<R: Read> Input<'_> for ReaderInput<R> {
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn read(&mut self, len: usize) -> Result<Vec<u8>> {
+        // Grow the buffer as bytes arrive rather than trusting a length read
+        // from the input
+        let mut result = Vec::new();
+        if len > 0 {
+            result.extend(self.peeked.take());
+        }
+        (&mut self.reader)
+            .take((len - result.len()) as u64)
+            .read_to_end(&mut result)?;
+        if result.len() < len {
+            return Err(Error::UnexpectedEof);
+        }
+        self.position += len;
+        Ok(result)
+    }
+
+    fn at_end(&mut self) -> Result<bool> {
+        if self.peeked.is_some() {
+            return Ok(false);
+        }
+        let mut byte = [0];
+        loop {
+            match self.reader.read(&mut byte) {
+                Ok(0) => return Ok(true),
+                Ok(_) => {
+                    self.peeked = Some(byte[0]);
+                    return Ok(false);
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
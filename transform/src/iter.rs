@@ -0,0 +1,78 @@
+use crate::de::BytesDeserializer;
+use crate::input::{Input, ReaderInput, SliceInput};
+use crate::{Config, Result};
+use serde::de::DeserializeOwned;
+use std::io;
+use std::marker::PhantomData;
+
+/// Iterator over records encoded back to back.
+///
+/// It stops cleanly when the input ends between records. A record cut short
+/// by the end of the input yields `Error::UnexpectedEof`, after which the
+/// iterator is exhausted.
+pub struct RecordIter<I, T> {
+    de: BytesDeserializer<I>,
+    done: bool,
+    record: PhantomData<fn() -> T>,
+}
+
+impl<'de, I: Input<'de>, T: DeserializeOwned> RecordIter<I, T> {
+    fn new(de: BytesDeserializer<I>) -> Self {
+        RecordIter {
+            de,
+            done: false,
+            record: PhantomData,
+        }
+    }
+}
+
+impl<'de, I: Input<'de>, T: DeserializeOwned> Iterator for RecordIter<I, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = match self.de.at_end() {
+            Ok(true) => {
+                self.done = true;
+                return None;
+            }
+            Ok(false) => self.de.deserialize(),
+            Err(err) => Err(err),
+        };
+        // The input can't be resynchronized after an error
+        self.done = result.is_err();
+        Some(result)
+    }
+}
+
+pub fn iter_from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> RecordIter<SliceInput<'_>, T> {
+    iter_from_bytes_with_config(bytes, Config::default())
+}
+
+pub fn iter_from_bytes_with_config<T: DeserializeOwned>(
+    bytes: &[u8],
+    config: Config,
+) -> RecordIter<SliceInput<'_>, T> {
+    RecordIter::new(BytesDeserializer::from_slice(bytes, config))
+}
+
+pub fn iter_from_reader<R, T>(reader: R) -> RecordIter<ReaderInput<R>, T>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    iter_from_reader_with_config(reader, Config::default())
+}
+
+pub fn iter_from_reader_with_config<R, T>(
+    reader: R,
+    config: Config,
+) -> RecordIter<ReaderInput<R>, T>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    RecordIter::new(BytesDeserializer::from_reader(reader, config))
+}
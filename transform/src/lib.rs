@@ -1,17 +1,70 @@
+mod align;
+#[cfg(feature = "async")]
+pub mod r#async;
+pub mod canonical_set;
+#[cfg(feature = "async")]
+mod codec;
+mod config;
 mod de;
 mod error;
+mod fixed;
+mod hash;
+mod incremental;
+mod input;
+mod iter;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod output;
+mod presence;
+mod raw;
 mod ser;
+pub mod table;
+mod tag;
+pub mod view;
 
-pub use de::from_bytes;
+#[cfg(feature = "async")]
+pub use codec::TransformCodec;
+pub use config::{Config, Layout, SeqLength, VariantEncoding};
+pub use de::{
+    from_bytes, from_bytes_with_config, from_reader, from_reader_with_config, BytesDeserializer,
+};
 pub use error::{Error, Result};
-pub use ser::to_bytes;
+pub use fixed::{BinLayout, FieldLayout, FixedSize};
+pub use incremental::{IncrementalDecoder, Progress};
+pub use input::{Input, ReaderInput, SliceInput};
+pub use iter::{
+    iter_from_bytes, iter_from_bytes_with_config, iter_from_reader, iter_from_reader_with_config,
+    RecordIter,
+};
+#[cfg(feature = "mmap")]
+pub use mmap::MappedFile;
+pub use raw::{Raw, RawBuf};
+pub use ser::{
+    serialized_size, serialized_size_with_config, to_bytes, to_bytes_into,
+    to_bytes_into_with_config, to_bytes_with_config, to_slice, to_slice_with_config, to_writer,
+    to_writer_with_config, BytesSerializer,
+};
+#[cfg(feature = "derive")]
+pub use transform_derive::BinLayout;
 
-pub fn load<'a, T>(data: Vec<u8>) -> Result<T>
+/// Decode a record from the start of `data`, ignoring any bytes after it.
+///
+/// Input shorter than `T::SIZE` is an `UnexpectedEof` error.
+pub fn load<'a, T>(data: &'a [u8]) -> Result<T>
 where
-    T: serde::de::Deserialize<'a> + serde::ser::Serialize + Default,
+    T: serde::Deserialize<'a> + FixedSize,
 {
-    let default = T::default();
-    let serialized = to_bytes(&default)?;
-    let data = &data[..serialized.len()];
-    from_bytes(data)
+    load_with_rest(data).map(|(value, _)| value)
+}
+
+/// Like `load`, but also return the bytes after the record.
+pub fn load_with_rest<'a, T>(data: &'a [u8]) -> Result<(T, &'a [u8])>
+where
+    T: serde::Deserialize<'a> + FixedSize,
+{
+    if data.len() < T::SIZE {
+        return Err(Error::UnexpectedEof);
+    }
+    let (record, rest) = data.split_at(T::SIZE);
+    Ok((from_bytes(record)?, rest))
 }
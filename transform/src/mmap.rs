@@ -0,0 +1,54 @@
+use crate::de::decode_framed;
+use crate::{Config, Result};
+use memmap2::Mmap;
+use serde::de::Deserialize;
+use std::fs::File;
+use std::path::Path;
+
+/// A file mapped into memory for decoding.
+///
+/// Only the pages that decoding touches are read from disk, and borrowed
+/// types such as `&str` point straight into the mapping. The top-level value
+/// must be framed, as for `IncrementalDecoder`, so that its frame can be
+/// checked against the size of the file before any of its content is read.
+pub struct MappedFile {
+    mmap: Mmap,
+    config: Config,
+}
+
+impl MappedFile {
+    /// Map the file at `path`.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be changed, by this process or any other, while it
+    /// is mapped. Decoded values borrow from the mapping, so a change would
+    /// alter them under the borrow.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_with_config(path, Config::default())
+    }
+
+    /// Map the file at `path`, decoding with `config`.
+    ///
+    /// # Safety
+    ///
+    /// Same as `open`.
+    pub unsafe fn open_with_config<P: AsRef<Path>>(path: P, config: Config) -> Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the mapping is only ever read, and the caller guarantees
+        // that the file isn't changed while it is mapped
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(MappedFile { mmap, config })
+    }
+
+    /// The mapped bytes.
+    pub fn bytes(&self) -> &[u8] {
+        &self.mmap
+    }
+
+    /// Decode the value the file holds, borrowing from the mapping where the
+    /// type allows it.
+    pub fn decode<'a, T: Deserialize<'a>>(&'a self) -> Result<T> {
+        decode_framed(self.bytes(), self.config)
+    }
+}
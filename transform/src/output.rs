@@ -0,0 +1,230 @@
+use crate::{Error, Result};
+use std::io;
+
+// Destination of the bytes produced by the serializer
+pub trait Output {
+    // Number of bytes written so far
+    fn position(&self) -> usize;
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()>;
+
+    // Write bytes that will be patched once the value is done
+    fn placeholder(&mut self, bytes: &[u8]) -> Result<()> {
+        self.write(bytes)
+    }
+
+    // Overwrite bytes written earlier, used to back-patch frame lengths
+    fn patch(&mut self, offset: usize, bytes: &[u8]) -> Result<()>;
+
+    // Discard everything written after `position`
+    fn truncate(&mut self, position: usize) -> Result<()>;
+}
+
+impl Output for Vec<u8> {
+    fn position(&self) -> usize {
+        self.len()
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn patch(&mut self, offset: usize, bytes: &[u8]) -> Result<()> {
+        self.get_mut(offset..offset + bytes.len())
+            .ok_or(Error::InvalidData)?
+            .copy_from_slice(bytes);
+        Ok(())
+    }
+
+    fn truncate(&mut self, position: usize) -> Result<()> {
+        Vec::truncate(self, position);
+        Ok(())
+    }
+}
+
+// Writes into a caller-provided slice, failing once it is full
+pub(crate) struct SliceOutput<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceOutput<'a> {
+    pub(crate) fn new(buffer: &'a mut [u8]) -> Self {
+        SliceOutput { buffer, len: 0 }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl Output for SliceOutput<'_> {
+    fn position(&self) -> usize {
+        self.len
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        let end = self.len + bytes.len();
+        self.buffer
+            .get_mut(self.len..end)
+            .ok_or(Error::BufferFull)?
+            .copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+
+    fn patch(&mut self, offset: usize, bytes: &[u8]) -> Result<()> {
+        self.buffer[..self.len]
+            .get_mut(offset..offset + bytes.len())
+            .ok_or(Error::InvalidData)?
+            .copy_from_slice(bytes);
+        Ok(())
+    }
+
+    fn truncate(&mut self, position: usize) -> Result<()> {
+        self.len = self.len.min(position);
+        Ok(())
+    }
+}
+
+// Counts the bytes without storing them. When recording, it also keeps the
+// final value of every placeholder, in the order they were written, so that
+// a second pass can write them in place of the placeholders.
+#[derive(Default)]
+pub(crate) struct SizeCounter {
+    len: usize,
+    patches: Option<Vec<u8>>,
+    // Offset and index into `patches` of the placeholders not patched yet
+    pending: Vec<(usize, usize)>,
+}
+
+impl SizeCounter {
+    pub(crate) fn recording() -> Self {
+        SizeCounter {
+            patches: Some(Vec::new()),
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn into_patches(self) -> Vec<u8> {
+        self.patches.unwrap_or_default()
+    }
+}
+
+impl Output for SizeCounter {
+    fn position(&self) -> usize {
+        self.len
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.len += bytes.len();
+        Ok(())
+    }
+
+    fn placeholder(&mut self, bytes: &[u8]) -> Result<()> {
+        if let Some(patches) = &mut self.patches {
+            self.pending.push((self.len, patches.len()));
+            patches.extend_from_slice(bytes);
+        }
+        self.write(bytes)
+    }
+
+    fn patch(&mut self, offset: usize, bytes: &[u8]) -> Result<()> {
+        let Some(patches) = &mut self.patches else {
+            return Ok(());
+        };
+        // Placeholders are patched innermost first
+        let index = self
+            .pending
+            .iter()
+            .rposition(|(pending, _)| *pending == offset)
+            .ok_or(Error::InvalidData)?;
+        let (_, start) = self.pending.remove(index);
+        patches
+            .get_mut(start..start + bytes.len())
+            .ok_or(Error::InvalidData)?
+            .copy_from_slice(bytes);
+        Ok(())
+    }
+
+    fn truncate(&mut self, position: usize) -> Result<()> {
+        self.len = self.len.min(position);
+        // Placeholders aren't kept by offset, so a recording can only be
+        // rolled back to its start
+        if let Some(patches) = &mut self.patches {
+            if position > 0 {
+                return Err(Error::Unimplemented);
+            }
+            patches.clear();
+            self.pending.clear();
+        }
+        Ok(())
+    }
+}
+
+// Streams to a writer. Nothing can be patched once written, so the final
+// values recorded by a `SizeCounter` pass over the same value are written
+// in place of the placeholders. Every primitive is a separate write, so the
+// writer is buffered.
+pub(crate) struct WriterOutput<W: io::Write> {
+    writer: io::BufWriter<W>,
+    position: usize,
+    // Recorded placeholder values, in the order they are written
+    patches: Vec<u8>,
+    next: usize,
+}
+
+impl<W: io::Write> WriterOutput<W> {
+    pub(crate) fn new(writer: W, patches: Vec<u8>) -> Self {
+        WriterOutput {
+            writer: io::BufWriter::new(writer),
+            position: 0,
+            patches,
+            next: 0,
+        }
+    }
+
+    pub(crate) fn flush(mut self) -> Result<()> {
+        io::Write::flush(&mut self.writer)?;
+        Ok(())
+    }
+}
+
+impl<W: io::Write> Output for WriterOutput<W> {
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        io::Write::write_all(&mut self.writer, bytes)?;
+        self.position += bytes.len();
+        Ok(())
+    }
+
+    fn placeholder(&mut self, bytes: &[u8]) -> Result<()> {
+        let end = self.next + bytes.len();
+        let patch = self.patches.get(self.next..end).ok_or(Error::InvalidData)?;
+        io::Write::write_all(&mut self.writer, patch)?;
+        self.next = end;
+        self.position += bytes.len();
+        Ok(())
+    }
+
+    fn patch(&mut self, _offset: usize, _bytes: &[u8]) -> Result<()> {
+        // Already written from the recorded patches
+        Ok(())
+    }
+
+    fn truncate(&mut self, position: usize) -> Result<()> {
+        if position == self.position {
+            Ok(())
+        } else {
+            Err(Error::Unimplemented)
+        }
+    }
+}
@@ -0,0 +1,271 @@
+// Struct fields in the option bitmap mode.
+//
+// A struct starts with its field count as a u16 and a bitmap holding one bit
+// per field. The bit is set when the field is present, which is always the
+// case except for a `None` field or one skipped by `skip_serializing_if`.
+// Option fields then skip their tag byte, and absent fields take no space at
+// all. Both wrappers forward everything else to the underlying serializer or
+// deserializer.
+use crate::de::BytesDeserializer;
+use crate::input::Input;
+use crate::output::Output;
+use crate::ser::BytesSerializer;
+use crate::{Error, Result};
+use serde::de;
+use serde::ser::{self, Serialize};
+use std::cell::RefCell;
+
+// Number of bytes in the bitmap of a struct with `len` fields
+pub(crate) fn bitmap_len(len: usize) -> usize {
+    len.div_ceil(8)
+}
+
+pub(crate) fn is_present(bitmap: &[u8], index: usize) -> bool {
+    bitmap
+        .get(index / 8)
+        .is_some_and(|byte| byte & (1 << (index % 8)) != 0)
+}
+
+// Field count and bits of a struct
+type Bitmap = (usize, Vec<u8>);
+
+// Neither serde's field count nor the fields the struct declares are reliable
+// once fields are skipped, and the bitmap goes in front of the fields. So
+// encoding runs twice, like for the C layout: a measuring pass records the
+// bitmap of every struct in the order they are opened, and the second pass
+// writes each one ahead of its fields.
+#[derive(Default)]
+pub(crate) struct Bitmaps {
+    // Bitmap of every struct, in the order they are opened
+    recorded: Vec<Bitmap>,
+    // Index into `recorded` and bitmap so far of the structs being encoded
+    open: Vec<(usize, Bitmap)>,
+    // Index of the next struct to open once measured
+    next: usize,
+    measured: bool,
+}
+
+impl Bitmaps {
+    pub(crate) fn is_measured(&self) -> bool {
+        self.measured
+    }
+
+    // Switch from the measuring pass to the encoding pass
+    pub(crate) fn finish_measuring(&mut self) {
+        self.open.clear();
+        self.next = 0;
+        self.measured = true;
+    }
+
+    // Returns the bitmap to write in front of the struct, or `None` while
+    // measuring
+    pub(crate) fn open(&mut self) -> Result<Option<&Bitmap>> {
+        let index = if self.measured {
+            self.next += 1;
+            self.next - 1
+        } else {
+            self.recorded.push(Bitmap::default());
+            self.recorded.len() - 1
+        };
+        self.open.push((index, Bitmap::default()));
+        if self.measured {
+            self.recorded.get(index).map(Some).ok_or(Error::InvalidData)
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub(crate) fn field(&mut self, present: bool) -> Result<()> {
+        let (_, (count, bits)) = self.open.last_mut().ok_or(Error::InvalidData)?;
+        if bits.len() < bitmap_len(*count + 1) {
+            bits.push(0);
+        }
+        if present {
+            bits[*count / 8] |= 1 << (*count % 8);
+        }
+        *count += 1;
+        Ok(())
+    }
+
+    // Fails if the fields seen don't match the bitmap written in front of
+    // them, which happens if the value changed between the passes
+    pub(crate) fn close(&mut self) -> Result<()> {
+        let (index, bitmap) = self.open.pop().ok_or(Error::InvalidData)?;
+        if !self.measured {
+            self.recorded[index] = bitmap;
+        } else if self.recorded[index] != bitmap {
+            return Err(Error::InvalidData);
+        }
+        Ok(())
+    }
+}
+
+pub(crate) struct FieldSerializer<'a, O> {
+    pub(crate) ser: &'a BytesSerializer<O>,
+    pub(crate) present: &'a RefCell<bool>,
+}
+
+impl<O: Output> FieldSerializer<'_, O> {
+    fn mark_present(&self) {
+        *self.present.borrow_mut() = true;
+    }
+}
+
+macro_rules! forward_present {
+    ($($method:ident($($arg:ident: $ty:ty),*) -> $ret:ty;)*) => {
+        $(
+            fn $method(self, $($arg: $ty),*) -> Result<$ret> {
+                self.mark_present();
+                self.ser.$method($($arg),*)
+            }
+        )*
+    };
+}
+
+impl<'a, O: Output> ser::Serializer for FieldSerializer<'a, O> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = &'a BytesSerializer<O>;
+    type SerializeTuple = &'a BytesSerializer<O>;
+    type SerializeTupleStruct = &'a BytesSerializer<O>;
+    type SerializeTupleVariant = &'a BytesSerializer<O>;
+    type SerializeMap = &'a BytesSerializer<O>;
+    type SerializeStruct = &'a BytesSerializer<O>;
+    type SerializeStructVariant = &'a BytesSerializer<O>;
+
+    forward_present! {
+        serialize_bool(v: bool) -> ();
+        serialize_i8(v: i8) -> ();
+        serialize_i16(v: i16) -> ();
+        serialize_i32(v: i32) -> ();
+        serialize_i64(v: i64) -> ();
+        serialize_u8(v: u8) -> ();
+        serialize_u16(v: u16) -> ();
+        serialize_u32(v: u32) -> ();
+        serialize_u64(v: u64) -> ();
+        serialize_f32(v: f32) -> ();
+        serialize_f64(v: f64) -> ();
+        serialize_char(v: char) -> ();
+        serialize_str(v: &str) -> ();
+        serialize_bytes(v: &[u8]) -> ();
+        serialize_unit() -> ();
+        serialize_unit_struct(name: &'static str) -> ();
+        serialize_unit_variant(name: &'static str, variant_index: u32, variant: &'static str) -> ();
+        serialize_seq(len: Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(len: usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(name: &'static str, len: usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(name: &'static str, variant_index: u32, variant: &'static str, len: usize) -> Self::SerializeTupleVariant;
+        serialize_map(len: Option<usize>) -> Self::SerializeMap;
+        serialize_struct(name: &'static str, len: usize) -> Self::SerializeStruct;
+        serialize_struct_variant(name: &'static str, variant_index: u32, variant: &'static str, len: usize) -> Self::SerializeStructVariant;
+    }
+
+    // An absent field leaves its bit clear and writes nothing
+    fn serialize_none(self) -> Result<()> {
+        Ok(())
+    }
+
+    // A present field sets its bit and writes the value without a tag
+    fn serialize_some<T>(self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.mark_present();
+        value.serialize(self.ser)
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.mark_present();
+        self.ser.serialize_newtype_struct(name, value)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.mark_present();
+        self.ser
+            .serialize_newtype_variant(name, variant_index, variant, value)
+    }
+}
+
+pub(crate) struct FieldDeserializer<'a, I> {
+    pub(crate) de: &'a BytesDeserializer<I>,
+    pub(crate) present: bool,
+}
+
+impl<I> FieldDeserializer<'_, I> {
+    // Only option fields may be absent
+    fn check_present(&self) -> Result<()> {
+        if self.present {
+            Ok(())
+        } else {
+            Err(Error::InvalidData)
+        }
+    }
+}
+
+macro_rules! forward_checked {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            fn $method<V: de::Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value> {
+                self.check_present()?;
+                self.de.$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+impl<'de, I: Input<'de>> de::Deserializer<'de> for FieldDeserializer<'_, I> {
+    type Error = Error;
+
+    forward_checked! {
+        deserialize_any();
+        deserialize_bool();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_newtype_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]);
+        deserialize_identifier();
+        deserialize_ignored_any();
+    }
+
+    // The bitmap takes the place of the option tag
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.present {
+            visitor.visit_some(self.de)
+        } else {
+            visitor.visit_none()
+        }
+    }
+}
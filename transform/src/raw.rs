@@ -0,0 +1,136 @@
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use std::fmt;
+
+// Newtype name the serializer and deserializer recognize raw values by
+pub(crate) const RAW: &str = "$transform::raw";
+
+/// The exact encoding of one framed value, kept as is.
+///
+/// Decoding captures the bytes of the value instead of decoding it, and
+/// encoding writes them back verbatim, so a value can be passed along without
+/// knowing its type. The value must be one that `IgnoredAny` can skip: a
+/// framed one in the framed layout with byte-length seqs, or any value in the
+/// self-describing mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Raw<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Raw<'a> {
+    /// Wrap bytes holding the encoding of one value.
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Raw { bytes }
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn to_raw_buf(&self) -> RawBuf {
+        RawBuf::new(self.bytes.to_vec())
+    }
+}
+
+/// An owned `Raw`, for input that can't be borrowed from, such as a reader.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RawBuf {
+    bytes: Vec<u8>,
+}
+
+impl RawBuf {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        RawBuf { bytes }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn as_raw(&self) -> Raw<'_> {
+        Raw::new(&self.bytes)
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+// Hands the bytes to the serializer, which writes them without a length
+struct RawBytes<'a>(&'a [u8]);
+
+impl Serialize for RawBytes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+impl Serialize for Raw<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(RAW, &RawBytes(self.bytes))
+    }
+}
+
+impl Serialize for RawBuf {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_raw().serialize(serializer)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Raw<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(RAW, RawVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for RawBuf {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(RAW, RawBufVisitor)
+    }
+}
+
+struct RawVisitor;
+
+impl<'de> de::Visitor<'de> for RawVisitor {
+    type Value = Raw<'de>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("borrowed raw bytes")
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Raw<'de>, D::Error> {
+        deserializer.deserialize_bytes(self)
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(self, bytes: &'de [u8]) -> Result<Raw<'de>, E> {
+        Ok(Raw::new(bytes))
+    }
+}
+
+struct RawBufVisitor;
+
+impl<'de> de::Visitor<'de> for RawBufVisitor {
+    type Value = RawBuf;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("raw bytes")
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<RawBuf, D::Error> {
+        deserializer.deserialize_byte_buf(self)
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<RawBuf, E> {
+        Ok(RawBuf::new(bytes.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<RawBuf, E> {
+        Ok(RawBuf::new(bytes))
+    }
+}
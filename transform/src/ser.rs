@@ -1,44 +1,390 @@
-use crate::{Error, Result};
+use crate::align::{padding, Alignments};
+use crate::canonical_set::SET;
+use crate::hash::fnv1a;
+use crate::output::{Output, SizeCounter, SliceOutput, WriterOutput};
+use crate::presence::{Bitmaps, FieldSerializer};
+use crate::raw::RAW;
+use crate::tag::Tag;
+use crate::{Config, Error, Result, SeqLength, VariantEncoding};
 use serde::ser::{self, Serialize};
 use std::cell::RefCell;
+use std::io;
 
-pub struct BytesSerializer {
-    buffer: RefCell<Vec<u8>>,
+// Encoded key and value of a map entry, or a set element and no value
+type Entry = (Vec<u8>, Vec<u8>);
+
+pub struct BytesSerializer<O = Vec<u8>> {
+    buffer: RefCell<O>,
     offsets: RefCell<Vec<usize>>,
+    counts: RefCell<Vec<u32>>,
+    alignments: RefCell<Alignments>,
+    bitmaps: RefCell<Bitmaps>,
+    // Position the current value started at
+    base: RefCell<usize>,
+    // Encoded entries of every open seq and map in the canonical mode, or
+    // `None` for seqs that keep their order
+    sorted: RefCell<Vec<Option<Vec<Entry>>>>,
+    // Whether the next seq is a set
+    set_next: RefCell<bool>,
+    // Whether the next bytes are a raw value to write verbatim
+    raw_next: RefCell<bool>,
+    config: Config,
 }
 
 impl BytesSerializer {
     pub fn new() -> Self {
+        Self::with_config(Config::default())
+    }
+
+    pub fn with_config(config: Config) -> Self {
+        Self::with_output(Vec::new(), config)
+    }
+
+    pub fn to_bytes<T: Serialize>(&self, value: &T) -> Result<Vec<u8>> {
+        self.buffer.borrow_mut().clear();
+        let result = self.serialize_value(value);
+        self.alignments.take();
+        self.bitmaps.take();
+        result?;
+        Ok(self.buffer.take())
+    }
+
+    /// Append the encoding of `value` to `out`, reusing its capacity. On
+    /// error `out` is left as it was.
+    pub fn serialize_into<T: Serialize>(&self, value: &T, out: &mut Vec<u8>) -> Result<()> {
+        std::mem::swap(&mut *self.buffer.borrow_mut(), out);
+        let result = self.serialize_value(value);
+        self.alignments.take();
+        self.bitmaps.take();
+        std::mem::swap(&mut *self.buffer.borrow_mut(), out);
+        result
+    }
+
+    /// Clear all state, including anything left behind by a failed encode.
+    pub fn reset(&self) {
+        self.buffer.borrow_mut().clear();
+        self.clear_state();
+    }
+}
+
+impl Default for BytesSerializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<O: Output> BytesSerializer<O> {
+    pub(crate) fn with_output(output: O, config: Config) -> Self {
         BytesSerializer {
-            buffer: RefCell::new(Vec::new()),
+            buffer: RefCell::new(output),
             offsets: RefCell::new(Vec::new()),
+            counts: RefCell::new(Vec::new()),
+            alignments: RefCell::new(Alignments::default()),
+            bitmaps: RefCell::new(Bitmaps::default()),
+            base: RefCell::new(0),
+            sorted: RefCell::new(Vec::new()),
+            set_next: RefCell::new(false),
+            raw_next: RefCell::new(false),
+            config,
         }
     }
 
-    pub fn to_bytes<T: Serialize>(&self, value: &T) -> Result<Vec<u8>> {
-        value.serialize(self)?;
-        Ok(self.buffer.take())
+    // Encode one value, leaving the output as it was on error
+    pub(crate) fn serialize_value<T: ?Sized + Serialize>(&self, value: &T) -> Result<()> {
+        let base = self.position();
+        *self.base.borrow_mut() = base;
+        let result = self.serialize_measured(value);
+        if result.is_err() {
+            self.clear_state();
+            // A writer can't take back what it was given, and the original
+            // error is the one to report either way
+            let _ = self.buffer.borrow_mut().truncate(base);
+        }
+        result
+    }
+
+    fn serialize_measured<T: ?Sized + Serialize>(&self, value: &T) -> Result<()> {
+        if self.is_measuring() {
+            // The first pass only measures the alignment of every container,
+            // or the option bitmap of every struct
+            value.serialize(self)?;
+            self.buffer.borrow_mut().truncate(*self.base.borrow())?;
+            self.alignments.borrow_mut().finish_measuring();
+            self.bitmaps.borrow_mut().finish_measuring();
+        }
+        value.serialize(self)
+    }
+
+    fn clear_state(&self) {
+        self.offsets.borrow_mut().clear();
+        self.counts.borrow_mut().clear();
+        self.bitmaps.take();
+        self.sorted.borrow_mut().clear();
+        *self.set_next.borrow_mut() = false;
+        *self.raw_next.borrow_mut() = false;
+        self.alignments.take();
+    }
+
+    fn write(&self, bytes: &[u8]) -> Result<()> {
+        self.buffer.borrow_mut().write(bytes)
+    }
+
+    fn position(&self) -> usize {
+        self.buffer.borrow().position()
+    }
+
+    fn is_measuring(&self) -> bool {
+        if self.config.is_aligned() {
+            !self.alignments.borrow().is_measured()
+        } else {
+            self.config.uses_option_bitmap() && !self.bitmaps.borrow().is_measured()
+        }
+    }
+
+    // Write the type tag of a value in the self-describing mode
+    fn write_tag(&self, tag: Tag) -> Result<()> {
+        if self.config.is_self_describing() {
+            self.write(&[tag as u8])
+        } else {
+            Ok(())
+        }
+    }
+
+    // Write bytes preceded by their u32 length
+    fn write_length_prefixed(&self, bytes: &[u8]) -> Result<()> {
+        let len = u32::try_from(bytes.len()).map_err(|_| Error::InvalidData)?;
+        self.write(&len.to_le_bytes())?;
+        self.write(bytes)
+    }
+
+    // Write a primitive, preceded by padding to its natural alignment in the
+    // C layout
+    fn write_primitive(&self, bytes: &[u8]) -> Result<()> {
+        if self.config.is_aligned() {
+            self.alignments.borrow_mut().primitive(bytes.len());
+            self.pad_to(bytes.len())?;
+        }
+        self.write(bytes)
+    }
+
+    fn pad_to(&self, align: usize) -> Result<()> {
+        // Alignment is relative to the start of the value
+        let len = padding(self.position() - *self.base.borrow(), align);
+        self.write(&vec![0; len])
+    }
+
+    // Types without a C equivalent can't be encoded in the C layout
+    fn check_not_aligned(&self) -> Result<()> {
+        if self.config.is_aligned() {
+            Err(Error::Unimplemented)
+        } else {
+            Ok(())
+        }
     }
 
     fn start_bytelen_encoding(&self) -> Result<&Self> {
         // Push the current buffer length to the offsets stack
-        self.offsets.borrow_mut().push(self.buffer.borrow().len());
+        self.offsets.borrow_mut().push(self.position());
         // Extend the buffer with 4 bytes for the length of the sequence
-        self.buffer.borrow_mut().extend(&0u32.to_le_bytes());
+        self.buffer.borrow_mut().placeholder(&0u32.to_le_bytes())?;
         Ok(self)
     }
 
     fn end_bytelen_encoding(&self) -> Result<()> {
         // Get the current buffer length
-        let buffer_len = self.buffer.borrow().len();
+        let buffer_len = self.position();
         // Get the last offset
         let offset = self.offsets.borrow_mut().pop().ok_or(Error::InvalidData)?;
         // Calculate the length of the sequence
-        let len = (buffer_len - offset - 4) as u32;
+        let len = u32::try_from(buffer_len - offset - 4).map_err(|_| Error::InvalidData)?;
         // Write the length to the buffer
-        self.buffer.borrow_mut()[offset..offset + 4].copy_from_slice(&len.to_le_bytes());
+        self.buffer.borrow_mut().patch(offset, &len.to_le_bytes())
+    }
+
+    fn start_count_encoding(&self) -> Result<&Self> {
+        // Push the current buffer length to the offsets stack
+        self.offsets.borrow_mut().push(self.position());
+        // Extend the buffer with 4 bytes for the number of elements
+        self.buffer.borrow_mut().placeholder(&0u32.to_le_bytes())?;
+        self.counts.borrow_mut().push(0);
+        Ok(self)
+    }
+
+    fn count_element(&self) -> Result<()> {
+        let mut counts = self.counts.borrow_mut();
+        let count = counts.last_mut().ok_or(Error::InvalidData)?;
+        *count = count.checked_add(1).ok_or(Error::InvalidData)?;
+        Ok(())
+    }
+
+    fn end_count_encoding(&self) -> Result<()> {
+        let offset = self.offsets.borrow_mut().pop().ok_or(Error::InvalidData)?;
+        let count = self.counts.borrow_mut().pop().ok_or(Error::InvalidData)?;
+        // Write the number of elements to the buffer
+        self.buffer.borrow_mut().patch(offset, &count.to_le_bytes())
+    }
+
+    fn start_seq_encoding(&self) -> Result<&Self> {
+        if self.config.canonical {
+            let is_set = self.set_next.take();
+            self.sorted
+                .borrow_mut()
+                .push(if is_set { Some(Vec::new()) } else { None });
+        }
+        match self.config.seq_length {
+            SeqLength::Bytes => self.start_bytelen_encoding(),
+            SeqLength::Elements => self.start_count_encoding(),
+        }
+    }
+
+    fn seq_element(&self) -> Result<()> {
+        if self.config.seq_length == SeqLength::Elements {
+            self.count_element()?;
+        }
+        Ok(())
+    }
+
+    fn end_seq_encoding(&self) -> Result<()> {
+        if self.config.canonical {
+            self.end_sorted_encoding()?;
+        }
+        match self.config.seq_length {
+            SeqLength::Bytes => self.end_bytelen_encoding(),
+            SeqLength::Elements => self.end_count_encoding(),
+        }
+    }
+
+    // Encode a map key or value, or a set element, on its own so that it can
+    // be sorted
+    fn encode_entry<T: ?Sized + Serialize>(&self, value: &T) -> Result<Vec<u8>> {
+        let ser = BytesSerializer::with_config(self.config);
+        ser.serialize_value(value)?;
+        Ok(ser.buffer.take())
+    }
+
+    // Whether the innermost seq or map has its entries sorted
+    fn is_sorting(&self) -> bool {
+        matches!(self.sorted.borrow().last(), Some(Some(_)))
+    }
+
+    fn sort_entry(&self, key: Vec<u8>) -> Result<()> {
+        let mut sorted = self.sorted.borrow_mut();
+        let entries = sorted.last_mut().and_then(Option::as_mut);
+        entries.ok_or(Error::InvalidData)?.push((key, Vec::new()));
         Ok(())
     }
+
+    fn sort_value(&self, value: Vec<u8>) -> Result<()> {
+        let mut sorted = self.sorted.borrow_mut();
+        let entries = sorted.last_mut().and_then(Option::as_mut);
+        let entry = entries.and_then(|entries| entries.last_mut());
+        entry.ok_or(Error::InvalidData)?.1 = value;
+        Ok(())
+    }
+
+    // Write the buffered entries of the innermost seq or map in the order of
+    // their encoded keys
+    fn end_sorted_encoding(&self) -> Result<()> {
+        let Some(mut entries) = self.sorted.borrow_mut().pop().ok_or(Error::InvalidData)? else {
+            return Ok(());
+        };
+        entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        if entries.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            return Err(Error::Custom(
+                "duplicate key in canonical encoding".to_string(),
+            ));
+        }
+        for (key, value) in entries {
+            self.write(&key)?;
+            self.write(&value)?;
+        }
+        Ok(())
+    }
+
+    // Structs, tuples and enum variants are only framed in the framed layout
+    fn start_container_encoding(&self) -> Result<&Self> {
+        if self.config.frames_containers() {
+            self.start_bytelen_encoding()
+        } else {
+            if self.config.is_aligned() {
+                let align = self.alignments.borrow_mut().open();
+                self.pad_to(align)?;
+            }
+            Ok(self)
+        }
+    }
+
+    fn end_container_encoding(&self) -> Result<()> {
+        if self.config.frames_containers() {
+            self.end_bytelen_encoding()
+        } else {
+            if self.config.is_aligned() {
+                // Trailing padding rounds the size up to the container alignment
+                let align = self.alignments.borrow_mut().close();
+                self.pad_to(align)?;
+            }
+            Ok(())
+        }
+    }
+
+    // Write the field count and the bitmap measured by the first pass
+    fn start_bitmap_encoding(&self) -> Result<()> {
+        let mut bitmaps = self.bitmaps.borrow_mut();
+        if let Some((count, bits)) = bitmaps.open()? {
+            let count = u16::try_from(*count).map_err(|_| Error::InvalidData)?;
+            self.write(&count.to_le_bytes())?;
+            self.write(bits)?;
+        }
+        Ok(())
+    }
+
+    // Encode the next field, leaving its bit clear if it is absent. Skipped
+    // fields have no value and are always absent.
+    fn encode_bitmap_field<T: ?Sized + Serialize>(&self, value: Option<&T>) -> Result<()> {
+        let present = RefCell::new(false);
+        if let Some(value) = value {
+            value.serialize(FieldSerializer {
+                ser: self,
+                present: &present,
+            })?;
+        }
+        self.bitmaps.borrow_mut().field(present.take())
+    }
+
+    fn end_bitmap_encoding(&self) -> Result<()> {
+        self.bitmaps.borrow_mut().close()
+    }
+
+    // In the self-describing mode, variants with content are a map holding a
+    // single entry keyed by the variant name
+    fn start_described_variant(&self, variant: &str) -> Result<&Self> {
+        self.write(&[Tag::Map as u8])?;
+        self.start_bytelen_encoding()?;
+        ser::Serializer::serialize_str(self, variant)?;
+        Ok(self)
+    }
+
+    fn start_variant_encoding(&self, variant_index: u32, variant: &str) -> Result<&Self> {
+        self.check_not_aligned()?;
+        self.start_container_encoding()?;
+        match self.config.variant_encoding {
+            VariantEncoding::Index => {
+                // If variant_index < u8::MAX, we can serialize it as a single byte
+                // Otherwise we return an error
+                if variant_index <= u8::MAX as u32 {
+                    self.write(&[variant_index as u8])?;
+                } else {
+                    return Err(Error::InvalidData);
+                }
+            }
+            VariantEncoding::Name => self.write_length_prefixed(variant.as_bytes())?,
+            VariantEncoding::Hash => {
+                let hash = fnv1a(variant.as_bytes());
+                self.write(&hash.to_le_bytes())?;
+            }
+        }
+        Ok(self)
+    }
 }
 
 pub fn to_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>> {
@@ -46,7 +392,83 @@ pub fn to_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>> {
     ser.to_bytes(value)
 }
 
-impl ser::Serializer for &BytesSerializer {
+pub fn to_bytes_with_config<T: Serialize>(value: &T, config: Config) -> Result<Vec<u8>> {
+    let ser = BytesSerializer::with_config(config);
+    ser.to_bytes(value)
+}
+
+pub fn to_writer<W, T>(writer: &mut W, value: &T) -> Result<()>
+where
+    W: io::Write,
+    T: Serialize,
+{
+    to_writer_with_config(writer, value, Config::default())
+}
+
+pub fn to_writer_with_config<W, T>(writer: &mut W, value: &T, config: Config) -> Result<()>
+where
+    W: io::Write,
+    T: Serialize,
+{
+    // A writer can't be back-patched, so a first pass works out every frame
+    // length without storing the encoding
+    let sizer = BytesSerializer::with_output(SizeCounter::recording(), config);
+    sizer.serialize_value(value)?;
+    let mut alignments = sizer.alignments.take();
+    alignments.finish_measuring();
+    let mut bitmaps = sizer.bitmaps.take();
+    bitmaps.finish_measuring();
+
+    let ser = BytesSerializer::with_output(
+        WriterOutput::new(writer, sizer.buffer.take().into_patches()),
+        config,
+    );
+    *ser.alignments.borrow_mut() = alignments;
+    *ser.bitmaps.borrow_mut() = bitmaps;
+    ser.serialize_value(value)?;
+    ser.buffer.into_inner().flush()
+}
+
+pub fn to_bytes_into<T: Serialize>(value: &T, out: &mut Vec<u8>) -> Result<()> {
+    to_bytes_into_with_config(value, out, Config::default())
+}
+
+pub fn to_bytes_into_with_config<T: Serialize>(
+    value: &T,
+    out: &mut Vec<u8>,
+    config: Config,
+) -> Result<()> {
+    let ser = BytesSerializer::with_config(config);
+    ser.serialize_into(value, out)
+}
+
+pub fn to_slice<T: Serialize>(value: &T, buffer: &mut [u8]) -> Result<usize> {
+    to_slice_with_config(value, buffer, Config::default())
+}
+
+pub fn to_slice_with_config<T: Serialize>(
+    value: &T,
+    buffer: &mut [u8],
+    config: Config,
+) -> Result<usize> {
+    let ser = BytesSerializer::with_output(SliceOutput::new(buffer), config);
+    ser.serialize_value(value)?;
+    let len = ser.buffer.borrow().len();
+    Ok(len)
+}
+
+pub fn serialized_size<T: Serialize>(value: &T) -> Result<usize> {
+    serialized_size_with_config(value, Config::default())
+}
+
+pub fn serialized_size_with_config<T: Serialize>(value: &T, config: Config) -> Result<usize> {
+    let sizer = BytesSerializer::with_output(SizeCounter::default(), config);
+    sizer.serialize_value(value)?;
+    let len = sizer.buffer.borrow().len();
+    Ok(len)
+}
+
+impl<O: Output> ser::Serializer for &BytesSerializer<O> {
     type Ok = ();
     type Error = Error;
 
@@ -58,82 +480,115 @@ impl ser::Serializer for &BytesSerializer {
     type SerializeStruct = Self;
     type SerializeStructVariant = Self;
 
-    // We will only implement serialization logic for u8, u32 and structs containing those
     fn serialize_bool(self, v: bool) -> Result<()> {
-        self.buffer.borrow_mut().push(if v { 1 } else { 0 });
-        Ok(())
+        self.write_tag(Tag::Bool)?;
+        self.write_primitive(&[v as u8])
     }
 
-    fn serialize_i8(self, _v: i8) -> Result<()> {
-        Err(Error::Unimplemented)
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.write_tag(Tag::I8)?;
+        self.write_primitive(&v.to_le_bytes())
     }
 
-    fn serialize_i16(self, _v: i16) -> Result<()> {
-        Err(Error::Unimplemented)
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.write_tag(Tag::I16)?;
+        self.write_primitive(&v.to_le_bytes())
     }
 
-    fn serialize_i32(self, _v: i32) -> Result<()> {
-        Err(Error::Unimplemented)
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.write_tag(Tag::I32)?;
+        self.write_primitive(&v.to_le_bytes())
     }
 
-    fn serialize_i64(self, _v: i64) -> Result<()> {
-        Err(Error::Unimplemented)
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.write_tag(Tag::I64)?;
+        self.write_primitive(&v.to_le_bytes())
     }
 
     fn serialize_u8(self, v: u8) -> Result<()> {
-        self.buffer.borrow_mut().push(v);
-        Ok(())
+        self.write_tag(Tag::U8)?;
+        self.write_primitive(&[v])
     }
 
-    fn serialize_u16(self, _v: u16) -> Result<()> {
-        Err(Error::Unimplemented)
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.write_tag(Tag::U16)?;
+        self.write_primitive(&v.to_le_bytes())
     }
 
     fn serialize_u32(self, v: u32) -> Result<()> {
-        self.buffer.borrow_mut().extend(&v.to_le_bytes());
-        Ok(())
+        self.write_tag(Tag::U32)?;
+        self.write_primitive(&v.to_le_bytes())
     }
 
-    fn serialize_u64(self, _v: u64) -> Result<()> {
-        Err(Error::Unimplemented)
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.write_tag(Tag::U64)?;
+        self.write_primitive(&v.to_le_bytes())
     }
 
-    fn serialize_f32(self, _v: f32) -> Result<()> {
-        Err(Error::Unimplemented)
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.write_tag(Tag::F32)?;
+        self.write_primitive(&v.to_le_bytes())
     }
 
-    fn serialize_f64(self, _v: f64) -> Result<()> {
-        Err(Error::Unimplemented)
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.write_tag(Tag::F64)?;
+        self.write_primitive(&v.to_le_bytes())
     }
 
-    fn serialize_char(self, _v: char) -> Result<()> {
-        Err(Error::Unimplemented)
+    // A char is encoded as its u32 scalar value
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.write_tag(Tag::Char)?;
+        self.write_primitive(&(v as u32).to_le_bytes())
     }
 
-    fn serialize_str(self, _v: &str) -> Result<()> {
-        Err(Error::Unimplemented)
+    // Strings and byte arrays are prefixed with their length in bytes
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.check_not_aligned()?;
+        self.write_tag(Tag::Str)?;
+        self.write_length_prefixed(v.as_bytes())
     }
 
-    fn serialize_bytes(self, _v: &[u8]) -> Result<()> {
-        Err(Error::Unimplemented)
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        // A raw value is already encoded
+        if self.raw_next.take() {
+            return self.write(v);
+        }
+        self.check_not_aligned()?;
+        self.write_tag(Tag::Bytes)?;
+        self.write_length_prefixed(v)
     }
 
+    // The tag of the self-describing mode stands in for the option byte
     fn serialize_none(self) -> Result<()> {
-        self.buffer.borrow_mut().push(0);
-        Ok(())
+        self.check_not_aligned()?;
+        if self.config.is_self_describing() {
+            return self.write_tag(Tag::None);
+        }
+        self.write(&[0])
     }
 
     fn serialize_some<T>(self, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
-        self.buffer.borrow_mut().push(1);
+        self.check_not_aligned()?;
+        if self.config.is_self_describing() {
+            self.write_tag(Tag::Some)?;
+        } else {
+            self.write(&[1])?;
+        }
         value.serialize(self)
     }
 
     fn serialize_unit(self) -> Result<()> {
-        self.buffer.borrow_mut().push(0);
-        Ok(())
+        if self.config.is_self_describing() {
+            return self.write_tag(Tag::Unit);
+        }
+        // Units are zero-sized in C
+        if self.config.is_aligned() {
+            return Ok(());
+        }
+        self.write_primitive(&[0])
     }
 
     // Unit Structs are structs without any fields
@@ -148,26 +603,27 @@ impl ser::Serializer for &BytesSerializer {
         self,
         _name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
     ) -> Result<()> {
-        let _ = self.start_bytelen_encoding();
-        // If variant_index < u8::MAX, we can serialize it as a single byte
-        // Otherwise we return an error
-        if variant_index <= u8::MAX as u32 {
-            self.buffer.borrow_mut().push(variant_index as u8);
-            self.end_bytelen_encoding()?;
-            Ok(())
-        } else {
-            Err(Error::InvalidData)
+        if self.config.is_self_describing() {
+            return self.serialize_str(variant);
         }
+        self.start_variant_encoding(variant_index, variant)?;
+        self.end_container_encoding()
     }
 
     // Newtype Structs are structs with a single unnamed field
     // They are created by `struct NewType(u32);`
-    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
+        if self.config.canonical && name == SET {
+            *self.set_next.borrow_mut() = true;
+        }
+        if name == RAW {
+            *self.raw_next.borrow_mut() = true;
+        }
         value.serialize(self)
     }
 
@@ -177,33 +633,37 @@ impl ser::Serializer for &BytesSerializer {
         self,
         _name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         value: &T,
     ) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
-        let _ = self.start_bytelen_encoding();
-        // If variant_index < u8::MAX, we can serialize it as a single byte
-        // Otherwise we return an error
-        if variant_index <= u8::MAX as u32 {
-            self.buffer.borrow_mut().push(variant_index as u8);
-        } else {
-            return Err(Error::InvalidData);
+        if self.config.is_self_describing() {
+            self.start_described_variant(variant)?;
+            value.serialize(self)?;
+            return self.end_bytelen_encoding();
         }
-        value.serialize(self)
+        self.start_variant_encoding(variant_index, variant)?;
+        value.serialize(self)?;
+        self.end_container_encoding()
     }
 
     // Seqs are used for serializing sequences of values
     // They are created by `vec![1, 2, 3]`
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
-        self.start_bytelen_encoding()
+        self.check_not_aligned()?;
+        self.write_tag(Tag::Seq)?;
+        self.start_seq_encoding()
     }
 
     // Tuples are used for serializing fixed size sequences of values
     // They are created by `(1, 2, 3)`
-    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
-        self.start_bytelen_encoding()
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        if self.config.is_self_describing() {
+            return self.serialize_seq(Some(len));
+        }
+        self.start_container_encoding()
     }
 
     // Tuple Structs are used for serializing structs with unnamed fields
@@ -211,9 +671,9 @@ impl ser::Serializer for &BytesSerializer {
     fn serialize_tuple_struct(
         self,
         _name: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        self.start_bytelen_encoding()
+        self.serialize_tuple(len)
     }
 
     // Tuple Variants are used for serializing enum variants with unnamed fields
@@ -222,29 +682,38 @@ impl ser::Serializer for &BytesSerializer {
         self,
         _name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        let _ = self.start_bytelen_encoding();
-        if variant_index <= u8::MAX as u32 {
-            self.buffer.borrow_mut().push(variant_index as u8);
-        } else {
-            return Err(Error::InvalidData);
+        if self.config.is_self_describing() {
+            self.start_described_variant(variant)?;
+            return self.serialize_seq(Some(len));
         }
-        Ok(self)
+        self.start_variant_encoding(variant_index, variant)
     }
 
     // Maps are used for serializing maps
     // They are created by `HashMap::new()`
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
-        let _ = self.start_bytelen_encoding();
+        self.check_not_aligned()?;
+        self.write_tag(Tag::Map)?;
+        if self.config.canonical {
+            self.sorted.borrow_mut().push(Some(Vec::new()));
+        }
+        self.start_bytelen_encoding()?;
         Ok(self)
     }
 
     // Structs are used for serializing structs
     // They are created by `struct Struct { a: u32, b: u32 }`
-    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
-        let _ = self.start_bytelen_encoding();
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        if self.config.is_self_describing() {
+            return self.serialize_map(Some(len));
+        }
+        self.start_container_encoding()?;
+        if self.config.uses_option_bitmap() {
+            self.start_bitmap_encoding()?;
+        }
         Ok(self)
     }
 
@@ -254,20 +723,18 @@ impl ser::Serializer for &BytesSerializer {
         self,
         _name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        let _ = self.start_bytelen_encoding();
-        if variant_index <= u8::MAX as u32 {
-            self.buffer.borrow_mut().push(variant_index as u8);
-        } else {
-            return Err(Error::InvalidData);
+        if self.config.is_self_describing() {
+            self.start_described_variant(variant)?;
+            return self.serialize_map(Some(len));
         }
-        Ok(self)
+        self.start_variant_encoding(variant_index, variant)
     }
 }
 
-impl ser::SerializeSeq for &BytesSerializer {
+impl<O: Output> ser::SerializeSeq for &BytesSerializer<O> {
     type Ok = ();
     type Error = Error;
 
@@ -275,15 +742,19 @@ impl ser::SerializeSeq for &BytesSerializer {
     where
         T: ?Sized + Serialize,
     {
+        self.seq_element()?;
+        if self.is_sorting() {
+            return self.sort_entry(self.encode_entry(value)?);
+        }
         value.serialize(*self)
     }
 
     fn end(self) -> Result<()> {
-        self.end_bytelen_encoding()
+        self.end_seq_encoding()
     }
 }
 
-impl ser::SerializeTuple for &BytesSerializer {
+impl<O: Output> ser::SerializeTuple for &BytesSerializer<O> {
     type Ok = ();
     type Error = Error;
 
@@ -291,15 +762,21 @@ impl ser::SerializeTuple for &BytesSerializer {
     where
         T: ?Sized + Serialize,
     {
+        if self.config.is_self_describing() {
+            self.seq_element()?;
+        }
         value.serialize(*self)
     }
 
     fn end(self) -> Result<()> {
-        self.end_bytelen_encoding()
+        if self.config.is_self_describing() {
+            return self.end_seq_encoding();
+        }
+        self.end_container_encoding()
     }
 }
 
-impl ser::SerializeTupleStruct for &BytesSerializer {
+impl<O: Output> ser::SerializeTupleStruct for &BytesSerializer<O> {
     type Ok = ();
     type Error = Error;
 
@@ -307,15 +784,21 @@ impl ser::SerializeTupleStruct for &BytesSerializer {
     where
         T: ?Sized + Serialize,
     {
+        if self.config.is_self_describing() {
+            self.seq_element()?;
+        }
         value.serialize(*self)
     }
 
     fn end(self) -> Result<()> {
-        self.end_bytelen_encoding()
+        if self.config.is_self_describing() {
+            return self.end_seq_encoding();
+        }
+        self.end_container_encoding()
     }
 }
 
-impl ser::SerializeTupleVariant for &BytesSerializer {
+impl<O: Output> ser::SerializeTupleVariant for &BytesSerializer<O> {
     type Ok = ();
     type Error = Error;
 
@@ -323,15 +806,22 @@ impl ser::SerializeTupleVariant for &BytesSerializer {
     where
         T: ?Sized + Serialize,
     {
+        if self.config.is_self_describing() {
+            self.seq_element()?;
+        }
         value.serialize(*self)
     }
 
     fn end(self) -> Result<()> {
-        self.end_bytelen_encoding()
+        if self.config.is_self_describing() {
+            self.end_seq_encoding()?;
+            return self.end_bytelen_encoding();
+        }
+        self.end_container_encoding()
     }
 }
 
-impl ser::SerializeMap for &BytesSerializer {
+impl<O: Output> ser::SerializeMap for &BytesSerializer<O> {
     type Ok = ();
     type Error = Error;
 
@@ -339,6 +829,9 @@ impl ser::SerializeMap for &BytesSerializer {
     where
         T: ?Sized + Serialize,
     {
+        if self.config.canonical {
+            return self.sort_entry(self.encode_entry(key)?);
+        }
         key.serialize(*self)
     }
 
@@ -346,42 +839,76 @@ impl ser::SerializeMap for &BytesSerializer {
     where
         T: ?Sized + Serialize,
     {
+        if self.config.canonical {
+            return self.sort_value(self.encode_entry(value)?);
+        }
         value.serialize(*self)
     }
 
     fn end(self) -> Result<()> {
+        if self.config.canonical {
+            self.end_sorted_encoding()?;
+        }
         self.end_bytelen_encoding()
     }
 }
 
-impl ser::SerializeStruct for &BytesSerializer {
+impl<O: Output> ser::SerializeStruct for &BytesSerializer<O> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(*self)
+        if self.config.is_self_describing() {
+            return ser::SerializeMap::serialize_entry(self, key, value);
+        }
+        if !self.config.uses_option_bitmap() {
+            return value.serialize(*self);
+        }
+        self.encode_bitmap_field(Some(value))
+    }
+
+    // A field left out by `skip_serializing_if` still takes a bit in the
+    // option bitmap, so that the fields after it keep theirs
+    fn skip_field(&mut self, _key: &'static str) -> Result<()> {
+        if self.config.uses_option_bitmap() {
+            self.encode_bitmap_field::<()>(None)?;
+        }
+        Ok(())
     }
 
     fn end(self) -> Result<()> {
-        self.end_bytelen_encoding()
+        if self.config.is_self_describing() {
+            return ser::SerializeMap::end(self);
+        }
+        if self.config.uses_option_bitmap() {
+            self.end_bitmap_encoding()?;
+        }
+        self.end_container_encoding()
     }
 }
 
-impl ser::SerializeStructVariant for &BytesSerializer {
+impl<O: Output> ser::SerializeStructVariant for &BytesSerializer<O> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
+        if self.config.is_self_describing() {
+            return ser::SerializeMap::serialize_entry(self, key, value);
+        }
         value.serialize(*self)
     }
 
     fn end(self) -> Result<()> {
-        self.end_bytelen_encoding()
+        if self.config.is_self_describing() {
+            ser::SerializeMap::end(self)?;
+            return self.end_bytelen_encoding();
+        }
+        self.end_container_encoding()
     }
 }
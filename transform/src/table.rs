@@ -0,0 +1,204 @@
+//! Flat files of fixed-size records.
+use crate::{load, load_with_rest, to_bytes, BinLayout, Error, FixedSize, Result};
+use serde::de::Deserialize;
+use serde::ser::Serialize;
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+
+/// The size of the optional header: the u32 record size followed by the u32
+/// fingerprint of the record layout.
+pub const HEADER_SIZE: usize = 8;
+
+// Check that a header was written for the layout of `T`
+fn check_header<T: BinLayout>(bytes: &[u8]) -> Result<()> {
+    if bytes.len() < HEADER_SIZE {
+        return Err(Error::UnexpectedEof);
+    }
+    let (size, rest) = load_with_rest::<u32>(bytes)?;
+    let hash: u32 = load(rest)?;
+    if size as usize != T::SIZE || hash != T::FINGERPRINT {
+        return Err(Error::InvalidData);
+    }
+    Ok(())
+}
+
+/// Writes records back to back, so that record `i` starts `i * T::SIZE`
+/// bytes after the header, if there is one.
+///
+/// To append to an existing table, open it in append mode and use
+/// `append_with_header` if it has a header, or `new` otherwise.
+pub struct TableWriter<W> {
+    writer: W,
+    record_size: Option<usize>,
+    len: usize,
+}
+
+impl<W: Write> TableWriter<W> {
+    /// Start a table without a header.
+    pub fn new(writer: W) -> Self {
+        TableWriter {
+            writer,
+            record_size: None,
+            len: 0,
+        }
+    }
+
+    /// Start a table with a header recording the size and a fingerprint of
+    /// the layout of `T`, which `TableReader::with_header` checks.
+    pub fn with_header<T: BinLayout>(mut writer: W) -> Result<Self> {
+        writer.write_all(&(T::SIZE as u32).to_le_bytes())?;
+        writer.write_all(&T::FINGERPRINT.to_le_bytes())?;
+        Ok(TableWriter {
+            writer,
+            record_size: Some(T::SIZE),
+            len: 0,
+        })
+    }
+
+    /// Append a record. Every record of a table must have the same size.
+    pub fn append<T: Serialize + FixedSize>(&mut self, value: &T) -> Result<()> {
+        if *self.record_size.get_or_insert(T::SIZE) != T::SIZE {
+            return Err(Error::InvalidData);
+        }
+        let bytes = to_bytes(value)?;
+        if bytes.len() != T::SIZE {
+            return Err(Error::InvalidData);
+        }
+        self.writer.write_all(&bytes)?;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// The number of records appended through this writer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Read + Write> TableWriter<W> {
+    /// Append to a table with a header, checking that it was written for the
+    /// layout of `T`. The header is read from `writer` first, so a file must
+    /// be opened for reading as well as appending.
+    pub fn append_with_header<T: BinLayout>(mut writer: W) -> Result<Self> {
+        let mut header = Vec::new();
+        (&mut writer)
+            .take(HEADER_SIZE as u64)
+            .read_to_end(&mut header)?;
+        check_header::<T>(&header)?;
+        Ok(TableWriter {
+            writer,
+            record_size: Some(T::SIZE),
+            len: 0,
+        })
+    }
+}
+
+/// Reads the records of a table written by `TableWriter`, each one in O(1).
+///
+/// Pair it with `MappedFile::bytes` to read large tables without loading
+/// them whole.
+pub struct TableReader<'a, T> {
+    records: &'a [u8],
+    value: PhantomData<fn() -> T>,
+}
+
+impl<'a, T> TableReader<'a, T>
+where
+    T: Deserialize<'a> + FixedSize,
+{
+    /// Read a table without a header.
+    pub fn new(bytes: &'a [u8]) -> Result<Self> {
+        // A trailing partial record means the table is truncated
+        if T::SIZE == 0 || !bytes.len().is_multiple_of(T::SIZE) {
+            return Err(Error::InvalidData);
+        }
+        Ok(TableReader {
+            records: bytes,
+            value: PhantomData,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len() / T::SIZE
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Decode record `index`, or return `None` past the end.
+    pub fn get(&self, index: usize) -> Result<Option<T>> {
+        if index >= self.len() {
+            return Ok(None);
+        }
+        load(&self.records[index * T::SIZE..]).map(Some)
+    }
+
+    pub fn iter(&self) -> TableIter<'a, T> {
+        TableIter {
+            records: self.records,
+            value: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> TableReader<'a, T>
+where
+    T: Deserialize<'a> + BinLayout,
+{
+    /// Read a table with a header, checking that it was written for the
+    /// layout of `T`.
+    pub fn with_header(bytes: &'a [u8]) -> Result<Self> {
+        check_header::<T>(bytes)?;
+        Self::new(&bytes[HEADER_SIZE..])
+    }
+}
+
+impl<'a, T> IntoIterator for &TableReader<'a, T>
+where
+    T: Deserialize<'a> + FixedSize,
+{
+    type Item = Result<T>;
+    type IntoIter = TableIter<'a, T>;
+
+    fn into_iter(self) -> TableIter<'a, T> {
+        self.iter()
+    }
+}
+
+/// Decodes the records of a table in order.
+pub struct TableIter<'a, T> {
+    records: &'a [u8],
+    value: PhantomData<fn() -> T>,
+}
+
+impl<'a, T> Iterator for TableIter<'a, T>
+where
+    T: Deserialize<'a> + FixedSize,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        if self.records.is_empty() {
+            return None;
+        }
+        let (record, rest) = self.records.split_at(T::SIZE);
+        self.records = rest;
+        Some(load(record))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.records.len() / T::SIZE;
+        (len, Some(len))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for TableIter<'a, T> where T: Deserialize<'a> + FixedSize {}
@@ -0,0 +1,57 @@
+use crate::{Error, Result};
+
+// Type tag written in front of every value in the self-describing mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Tag {
+    Unit = 0,
+    Bool = 1,
+    I8 = 2,
+    I16 = 3,
+    I32 = 4,
+    I64 = 5,
+    U8 = 6,
+    U16 = 7,
+    U32 = 8,
+    U64 = 9,
+    F32 = 10,
+    F64 = 11,
+    Char = 12,
+    Str = 13,
+    Bytes = 14,
+    None = 15,
+    Some = 16,
+    Seq = 17,
+    Map = 18,
+}
+
+impl Tag {
+    // Tags in the order of their value
+    const ALL: [Tag; 19] = [
+        Tag::Unit,
+        Tag::Bool,
+        Tag::I8,
+        Tag::I16,
+        Tag::I32,
+        Tag::I64,
+        Tag::U8,
+        Tag::U16,
+        Tag::U32,
+        Tag::U64,
+        Tag::F32,
+        Tag::F64,
+        Tag::Char,
+        Tag::Str,
+        Tag::Bytes,
+        Tag::None,
+        Tag::Some,
+        Tag::Seq,
+        Tag::Map,
+    ];
+
+    pub(crate) fn from_byte(byte: u8) -> Result<Tag> {
+        Self::ALL
+            .get(byte as usize)
+            .copied()
+            .ok_or(Error::InvalidData)
+    }
+}
@@ -0,0 +1,205 @@
+//! Random access into encoded values.
+use crate::de::decode_exact;
+use crate::tag::Tag;
+use crate::{BinLayout, Config, Error, FixedSize, Result, SeqLength};
+use serde::de::Deserialize;
+
+/// An encoded value that can be stepped into without decoding it.
+///
+/// In the self-describing mode the type tags give the size of every value,
+/// so any child can be reached with `element` and `entry`. Otherwise the
+/// encoding doesn't say where a child ends, so the type of the value has to:
+/// `field` steps into a `BinLayout` struct at its field offsets, and
+/// `element_of` and `entry_of` into a seq or map of `FixedSize` elements or
+/// entries. This needs the framed layout with byte-length seqs and no option
+/// bitmap; other configurations are `Unimplemented`, and so are `element`
+/// and `entry`.
+#[derive(Clone, Copy, Debug)]
+pub struct Frame<'a> {
+    // The whole value, including its header
+    value: &'a [u8],
+    // The children of a self-describing seq or map
+    children: Option<&'a [u8]>,
+    config: Config,
+}
+
+impl<'a> Frame<'a> {
+    /// View the value at the start of `bytes`.
+    pub fn new(bytes: &'a [u8]) -> Result<Self> {
+        Self::with_config(bytes, Config::default())
+    }
+
+    pub fn with_config(bytes: &'a [u8], config: Config) -> Result<Self> {
+        if config.is_self_describing() {
+            Self::described(bytes, config)
+        } else {
+            Self::framed(bytes, config)
+        }
+    }
+
+    fn framed(bytes: &'a [u8], config: Config) -> Result<Self> {
+        check_walkable(config)?;
+        let len = read_u32(bytes, 0)?;
+        Ok(Self::sized(slice(bytes, 0, 4 + len)?, config))
+    }
+
+    // A value whose extent is known from its type
+    fn sized(value: &'a [u8], config: Config) -> Self {
+        Frame {
+            value,
+            children: None,
+            config,
+        }
+    }
+
+    fn described(bytes: &'a [u8], config: Config) -> Result<Self> {
+        let tag = Tag::from_byte(*bytes.first().ok_or(Error::UnexpectedEof)?)?;
+        let (len, has_children) = match tag {
+            Tag::Unit | Tag::None => (1, false),
+            Tag::Bool | Tag::I8 | Tag::U8 => (2, false),
+            Tag::I16 | Tag::U16 => (3, false),
+            Tag::I32 | Tag::U32 | Tag::F32 | Tag::Char => (5, false),
+            Tag::I64 | Tag::U64 | Tag::F64 => (9, false),
+            Tag::Str | Tag::Bytes => (5 + read_u32(bytes, 1)?, false),
+            Tag::Some => (1 + Self::described(&bytes[1..], config)?.value.len(), false),
+            Tag::Seq if config.seq_length == SeqLength::Elements => {
+                // Only the element count is known, so walk the elements
+                let mut len = 5;
+                for _ in 0..read_u32(bytes, 1)? {
+                    let rest = bytes.get(len..).ok_or(Error::UnexpectedEof)?;
+                    len += Self::described(rest, config)?.value.len();
+                }
+                (len, true)
+            }
+            Tag::Seq | Tag::Map => (5 + read_u32(bytes, 1)?, true),
+        };
+        let value = slice(bytes, 0, len)?;
+        Ok(Frame {
+            value,
+            children: has_children.then(|| &value[5..]),
+            config,
+        })
+    }
+
+    /// The encoded value, including its header.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.value
+    }
+
+    /// Step into element `n` of a self-describing seq or tuple. Returns `None`
+    /// past the last element. Structs are maps in this mode, and their fields
+    /// are reached with `entry`.
+    pub fn element(&self, n: usize) -> Result<Option<Frame<'a>>> {
+        match self.child(n)? {
+            Some(rest) => Self::with_config(rest, self.config).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Step into the key and value of entry `n` of a self-describing map.
+    pub fn entry(&self, n: usize) -> Result<Option<(Frame<'a>, Frame<'a>)>> {
+        // Keys and values alternate
+        let Some(rest) = self.child(2 * n)? else {
+            return Ok(None);
+        };
+        let key = Self::with_config(rest, self.config)?;
+        let value = Self::with_config(&rest[key.value.len()..], self.config)?;
+        Ok(Some((key, value)))
+    }
+
+    /// Step into field `n` of a struct laid out as `T`. Returns `None` past
+    /// the last field.
+    pub fn field<T: BinLayout>(&self, n: usize) -> Result<Option<Frame<'a>>> {
+        check_walkable(self.config)?;
+        if self.value.len() != T::SIZE {
+            return Err(Error::InvalidData);
+        }
+        let Some(field) = T::FIELDS.get(n) else {
+            return Ok(None);
+        };
+        let value = slice(self.value, field.offset, field.size)?;
+        Ok(Some(Self::sized(value, self.config)))
+    }
+
+    /// Step into element `n` of a seq whose elements are all `T`. Returns
+    /// `None` past the last element.
+    pub fn element_of<T: FixedSize>(&self, n: usize) -> Result<Option<Frame<'a>>> {
+        let element = self.sized_child(n, T::SIZE)?;
+        Ok(element.map(|value| Self::sized(value, self.config)))
+    }
+
+    /// Step into the key and value of entry `n` of a map whose keys are all
+    /// `K` and values all `V`. Returns `None` past the last entry.
+    pub fn entry_of<K: FixedSize, V: FixedSize>(
+        &self,
+        n: usize,
+    ) -> Result<Option<(Frame<'a>, Frame<'a>)>> {
+        let Some(entry) = self.sized_child(n, K::SIZE + V::SIZE)? else {
+            return Ok(None);
+        };
+        let (key, value) = entry.split_at(K::SIZE);
+        Ok(Some((
+            Self::sized(key, self.config),
+            Self::sized(value, self.config),
+        )))
+    }
+
+    /// Decode the value, borrowing from the underlying bytes where the type
+    /// allows it.
+    pub fn decode<T: Deserialize<'a>>(&self) -> Result<T> {
+        decode_exact(self.value, self.config)
+    }
+
+    // Child `n` of a framed seq or map whose children are all `size` bytes
+    fn sized_child(&self, n: usize, size: usize) -> Result<Option<&'a [u8]>> {
+        check_walkable(self.config)?;
+        let children = slice(self.value, 4, read_u32(self.value, 0)?)?;
+        if !children.len().is_multiple_of(size) {
+            return Err(Error::InvalidData);
+        }
+        match n.checked_mul(size) {
+            Some(offset) if offset < children.len() => slice(children, offset, size).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    // The bytes from child `n` to the end of the children
+    fn child(&self, n: usize) -> Result<Option<&'a [u8]>> {
+        if !self.config.is_self_describing() {
+            return Err(Error::Unimplemented);
+        }
+        let mut rest = self.children.ok_or(Error::InvalidData)?;
+        for _ in 0..n {
+            if rest.is_empty() {
+                return Ok(None);
+            }
+            let child = Self::with_config(rest, self.config)?;
+            rest = &rest[child.value.len()..];
+        }
+        Ok((!rest.is_empty()).then_some(rest))
+    }
+}
+
+// Values can only be delimited by their type when containers carry a byte
+// length and the layout of a struct doesn't depend on its values
+fn check_walkable(config: Config) -> Result<()> {
+    let walkable = config.frames_containers()
+        && config.seq_length == SeqLength::Bytes
+        && !config.uses_option_bitmap()
+        && !config.is_self_describing();
+    if walkable {
+        Ok(())
+    } else {
+        Err(Error::Unimplemented)
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<usize> {
+    let header = slice(bytes, offset, 4)?;
+    Ok(u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize)
+}
+
+fn slice(bytes: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+    let end = offset.checked_add(len).ok_or(Error::InvalidData)?;
+    bytes.get(offset..end).ok_or(Error::UnexpectedEof)
+}
@@ -1,11 +1,35 @@
+use futures_util::{SinkExt, StreamExt};
+use serde::de::IgnoredAny;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use transform::{from_bytes, to_bytes};
+use std::collections::{HashMap, HashSet};
+use tokio_util::codec::{Framed, FramedRead, FramedWrite};
+use transform::r#async::{
+    from_async_reader, from_async_reader_with_config, to_async_writer, to_async_writer_with_config,
+};
+use transform::table::{TableReader, TableWriter, HEADER_SIZE};
+use transform::view::Frame;
+use transform::{
+    from_bytes, from_bytes_with_config, from_reader_with_config, iter_from_bytes, iter_from_reader,
+    load, load_with_rest, serialized_size, serialized_size_with_config, to_bytes, to_bytes_into,
+    to_bytes_with_config, to_slice_with_config, to_writer_with_config, BinLayout,
+    BytesDeserializer, BytesSerializer, Config, Error, FixedSize, IncrementalDecoder, Layout,
+    MappedFile, Progress, Raw, RawBuf, ReaderInput, RecordIter, SeqLength, SliceInput,
+    TransformCodec, VariantEncoding,
+};
 
 pub fn main() {
     let test_string = "hello".to_string();
     let result = to_bytes(&test_string);
-    assert!(result.is_err());
+    assert!(result.is_ok());
+    let back: String = from_bytes(&result.unwrap()).unwrap();
+    assert_eq!(back, test_string);
+
+    // Strings are prefixed with their u32 length in bytes, chars are their
+    // u32 scalar value
+    assert_eq!(to_bytes(&"hi").unwrap(), [2, 0, 0, 0, b'h', b'i']);
+    assert_eq!(to_bytes(&'é').unwrap(), 0xe9u32.to_le_bytes());
+    let back: char = from_bytes(&to_bytes(&'é').unwrap()).unwrap();
+    assert_eq!(back, 'é');
 
     let test_option: Option<u8> = Some(0);
     let result = to_bytes(&test_option);
@@ -16,8 +40,7 @@ pub fn main() {
     let test_unit = ();
     let result = to_bytes(&test_unit);
     assert!(result.is_ok());
-    let back: () = from_bytes(&result.unwrap()).unwrap();
-    assert_eq!(back, test_unit);
+    let (): () = from_bytes(&result.unwrap()).unwrap();
 
     #[derive(Serialize, Deserialize, Debug, PartialEq)]
     struct UnitStruct;
@@ -101,7 +124,7 @@ pub fn main() {
 
     // This will fail because we don't support Map for u8 & u32
     let mut test_map = HashMap::new();
-    test_map.insert(0 as u8, 1 as u32);
+    test_map.insert(0u8, 1u32);
     let result = to_bytes(&test_map);
     assert!(result.is_ok());
     let back: HashMap<u8, u32> = from_bytes(&result.unwrap()).unwrap();
@@ -136,4 +159,1068 @@ pub fn main() {
     assert!(result.is_ok());
     let back: StructVariant = from_bytes(&result.unwrap()).unwrap();
     assert_eq!(back, test_struct_variant);
+
+    // Packed layout drops the frames of structs, tuples and enum variants
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Record {
+        a: (u8, u32),
+        b: StructVariant,
+        c: NewTypeVariant,
+        d: Vec<u32>,
+    }
+
+    let packed = Config::new().layout(Layout::Packed);
+    let test_record = Record {
+        a: (0, 1),
+        b: StructVariant::A { a: 2, b: 3 },
+        c: NewTypeVariant::A(4),
+        d: vec![5, 6],
+    };
+    let framed = to_bytes(&test_record).unwrap();
+    let back: Record = from_bytes(&framed).unwrap();
+    assert_eq!(back, test_record);
+    let result = to_bytes_with_config(&test_record, packed).unwrap();
+    assert_eq!(result.len(), framed.len() - 4 * 4);
+    let back: Record = from_bytes_with_config(&result, packed).unwrap();
+    assert_eq!(back, test_record);
+
+    // Element-count prefixes give the decoder an exact size hint
+    let counted = Config::new().seq_length(SeqLength::Elements);
+    let test_nested: Vec<Vec<u32>> = vec![vec![0, 1], vec![], vec![2]];
+    let result = to_bytes_with_config(&test_nested, counted).unwrap();
+    assert_eq!(&result[..4], &3u32.to_le_bytes());
+    let back: Vec<Vec<u32>> = from_bytes_with_config(&result, counted).unwrap();
+    assert_eq!(back, test_nested);
+
+    // The C layout matches the `#[repr(C)]` layout of the same struct
+    #[repr(C)]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Inner {
+        c: u8,
+        d: u32,
+    }
+
+    #[repr(C)]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Firmware {
+        a: u8,
+        b: Inner,
+        e: u16,
+        f: [i64; 2],
+        g: bool,
+    }
+
+    let c_layout = Config::new().layout(Layout::C);
+    let test_firmware = Firmware {
+        a: 1,
+        b: Inner { c: 2, d: 3 },
+        e: 4,
+        f: [-5, 6],
+        g: true,
+    };
+    let result = to_bytes_with_config(&test_firmware, c_layout).unwrap();
+    assert_eq!(result.len(), std::mem::size_of::<Firmware>());
+    assert_eq!(result[std::mem::offset_of!(Firmware, b.d)], 3);
+    assert_eq!(result[std::mem::offset_of!(Firmware, e)], 4);
+    assert_eq!(result[std::mem::offset_of!(Firmware, g)], 1);
+    let back: Firmware = from_bytes_with_config(&result, c_layout).unwrap();
+    assert_eq!(back, test_firmware);
+    let mut corrupted = result.clone();
+    corrupted[1] = 0xff;
+    assert!(from_bytes_with_config::<Firmware>(&corrupted, c_layout).is_err());
+    assert!(to_bytes_with_config(&test_vec, c_layout).is_err());
+
+    // Fields that reject zero, and a struct aligned beyond its first field
+    #[repr(C)]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Wide {
+        a: u16,
+        b: u32,
+    }
+
+    #[repr(C)]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Checked {
+        id: std::num::NonZeroU32,
+        tag: u16,
+        wide: Wide,
+    }
+
+    let test_checked = Checked {
+        id: std::num::NonZeroU32::new(9).unwrap(),
+        tag: 1,
+        wide: Wide { a: 2, b: 3 },
+    };
+    let result = to_bytes_with_config(&test_checked, c_layout).unwrap();
+    assert_eq!(result.len(), std::mem::size_of::<Checked>());
+    let back: Checked = from_bytes_with_config(&result, c_layout).unwrap();
+    assert_eq!(back, test_checked);
+    let back: Checked = from_reader_with_config(&result[..], c_layout).unwrap();
+    assert_eq!(back, test_checked);
+    let mut zeroed = result.clone();
+    zeroed[..4].fill(0);
+    assert!(from_bytes_with_config::<Checked>(&zeroed, c_layout).is_err());
+
+    // A misplaced first guess reads a data byte as a bool
+    #[repr(C)]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Flagged {
+        v: u8,
+        w: u8,
+        x: u8,
+        y: u8,
+        f: bool,
+        c: u32,
+    }
+
+    #[repr(C)]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Outer {
+        a: u8,
+        i: Flagged,
+    }
+
+    let test_outer = Outer {
+        a: 1,
+        i: Flagged {
+            v: 0,
+            w: 7,
+            x: 0,
+            y: 0,
+            f: true,
+            c: 5,
+        },
+    };
+    let result = to_bytes_with_config(&test_outer, c_layout).unwrap();
+    assert_eq!(result.len(), std::mem::size_of::<Outer>());
+    let back: Outer = from_bytes_with_config(&result, c_layout).unwrap();
+    assert_eq!(back, test_outer);
+    let mut corrupted = result.clone();
+    corrupted[std::mem::offset_of!(Outer, i.f)] = 2;
+    assert!(from_bytes_with_config::<Outer>(&corrupted, c_layout).is_err());
+
+    // Unit fields are zero-sized
+    #[repr(C)]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Marked {
+        a: u8,
+        u: UnitStruct,
+        e: (),
+        b: u8,
+    }
+
+    let test_marked = Marked {
+        a: 1,
+        u: UnitStruct,
+        e: (),
+        b: 2,
+    };
+    let result = to_bytes_with_config(&test_marked, c_layout).unwrap();
+    assert_eq!(result.len(), std::mem::size_of::<Marked>());
+    assert_eq!(result[std::mem::offset_of!(Marked, b)], 2);
+    let back: Marked = from_bytes_with_config(&result, c_layout).unwrap();
+    assert_eq!(back, test_marked);
+
+    // Nested containers repeated many times take their alignment from the
+    // type, not from the values read
+    #[repr(C)]
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+    struct Packet {
+        a: u16,
+        b: u16,
+        f: bool,
+        x: u64,
+        y: u64,
+    }
+
+    #[repr(C)]
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+    struct Mid {
+        i: Packet,
+        z: u64,
+    }
+
+    #[repr(C)]
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+    struct Elem {
+        f: bool,
+        m: Mid,
+        w: u64,
+    }
+
+    #[repr(C)]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Top<T> {
+        h: u32,
+        items: T,
+    }
+
+    fn elem(n: usize) -> Elem {
+        let n = n as u64;
+        Elem {
+            f: n.is_multiple_of(3),
+            m: Mid {
+                i: Packet {
+                    a: n as u16,
+                    b: (n * 7) as u16,
+                    f: !n.is_multiple_of(2),
+                    x: (n << 40) | 1,
+                    y: u64::MAX - n,
+                },
+                z: n * 257,
+            },
+            w: n << 8,
+        }
+    }
+
+    let test_top = Top {
+        h: 0xdead,
+        items: std::array::from_fn::<[Elem; 8], 8, _>(|row| {
+            std::array::from_fn(|col| elem(row * 8 + col))
+        }),
+    };
+    let result = to_bytes_with_config(&test_top, c_layout).unwrap();
+    assert_eq!(result.len(), std::mem::size_of_val(&test_top));
+    let back: Top<[[Elem; 8]; 8]> = from_bytes_with_config(&result, c_layout).unwrap();
+    assert_eq!(back, test_top);
+    let test_top = Top {
+        h: 0xbeef,
+        items: std::array::from_fn::<[Elem; 16], 5, _>(|row| {
+            std::array::from_fn(|col| elem(row * 16 + col))
+        }),
+    };
+    let result = to_bytes_with_config(&test_top, c_layout).unwrap();
+    assert_eq!(result.len(), std::mem::size_of_val(&test_top));
+    let back: Top<[[Elem; 16]; 5]> = from_bytes_with_config(&result, c_layout).unwrap();
+    assert_eq!(back, test_top);
+
+    // Variants encoded by name or hash survive reordering the enum
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Reordered {
+        A { a: u8, b: u32 },
+        C(u32),
+        B,
+    }
+
+    for encoding in [VariantEncoding::Name, VariantEncoding::Hash] {
+        let config = Config::new().variant_encoding(encoding);
+        let result = to_bytes_with_config(&StructVariant::B, config).unwrap();
+        let back: Reordered = from_bytes_with_config(&result, config).unwrap();
+        assert_eq!(back, Reordered::B);
+        let result = to_bytes_with_config(&StructVariant::A { a: 0, b: 1 }, config).unwrap();
+        let back: Reordered = from_bytes_with_config(&result, config).unwrap();
+        assert_eq!(back, Reordered::A { a: 0, b: 1 });
+        let result = to_bytes_with_config(&Reordered::C(2), config).unwrap();
+        assert!(from_bytes_with_config::<StructVariant>(&result, config).is_err());
+    }
+
+    // The option bitmap replaces the tag byte of every option field
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Sparse {
+        a: Option<u32>,
+        b: u8,
+        c: Option<Option<u8>>,
+        d: Option<Vec<Option<u8>>>,
+        e: Option<u16>,
+    }
+
+    for layout in [Layout::Framed, Layout::Packed] {
+        let config = Config::new().layout(layout).option_bitmap(true);
+        let test_sparse = Sparse {
+            a: None,
+            b: 1,
+            c: Some(None),
+            d: Some(vec![Some(2), None]),
+            e: None,
+        };
+        // Four tag bytes saved, one bitmap byte and the u16 field count added
+        let result = to_bytes_with_config(&test_sparse, config).unwrap();
+        assert_eq!(
+            result.len() + 1,
+            to_bytes_with_config(&test_sparse, Config::new().layout(layout))
+                .unwrap()
+                .len()
+        );
+        let back: Sparse = from_bytes_with_config(&result, config).unwrap();
+        assert_eq!(back, test_sparse);
+    }
+
+    // Fields skipped when serializing keep their bit, and the field count on
+    // the wire doesn't depend on aliases
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Skipping {
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        a: Option<u8>,
+        b: Option<u8>,
+        #[serde(alias = "legacy_c")]
+        c: u8,
+        d: u8,
+        e: u8,
+        f: u8,
+        g: u8,
+        h: u8,
+        #[serde(skip_serializing, default)]
+        i: u8,
+    }
+
+    for layout in [Layout::Framed, Layout::Packed] {
+        let config = Config::new().layout(layout).option_bitmap(true);
+        let test_skipping = Skipping {
+            a: None,
+            b: Some(3),
+            c: 4,
+            d: 5,
+            e: 6,
+            f: 7,
+            g: 8,
+            h: 9,
+            i: 10,
+        };
+        let result = to_bytes_with_config(&test_skipping, config).unwrap();
+        let back: Skipping = from_bytes_with_config(&result, config).unwrap();
+        assert_eq!(
+            back,
+            Skipping {
+                i: 0,
+                ..test_skipping
+            }
+        );
+        let test_skipping = Skipping {
+            a: Some(1),
+            ..test_skipping
+        };
+        let result = to_bytes_with_config(&test_skipping, config).unwrap();
+        let back: Skipping = from_bytes_with_config(&result, config).unwrap();
+        assert_eq!(
+            back,
+            Skipping {
+                i: 0,
+                ..test_skipping
+            }
+        );
+    }
+
+    // A struct that gained a field no longer fits the bitmap of the old one
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Eight {
+        a: u8,
+        b: u8,
+        c: u8,
+        d: u8,
+        e: u8,
+        f: u8,
+        g: u8,
+        h: u8,
+    }
+
+    #[derive(Serialize)]
+    struct Nine {
+        a: u8,
+        b: u8,
+        c: u8,
+        d: u8,
+        e: u8,
+        f: u8,
+        g: u8,
+        h: u8,
+        i: Option<u8>,
+    }
+
+    let bitmap = Config::new().option_bitmap(true);
+    let test_eight = Eight {
+        a: 1,
+        b: 2,
+        c: 3,
+        d: 4,
+        e: 5,
+        f: 6,
+        g: 7,
+        h: 8,
+    };
+    let test_nine = Nine {
+        a: 1,
+        b: 1,
+        c: 2,
+        d: 3,
+        e: 4,
+        f: 5,
+        g: 6,
+        h: 7,
+        i: Some(9),
+    };
+    let result = to_bytes_with_config(&(test_nine, 8u8), bitmap).unwrap();
+    assert!(from_bytes_with_config::<(Eight, u8)>(&result, bitmap).is_err());
+    let result = to_bytes_with_config(&(&test_eight, 10u8), bitmap).unwrap();
+    let back: (Eight, u8) = from_bytes_with_config(&result, bitmap).unwrap();
+    assert_eq!(back, (test_eight, 10));
+
+    // Struct variants are encoded as usual, while any struct nested in them
+    // or in a field gets its own bitmap
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Shape {
+        Circle { r: Option<u8> },
+        Nested(Sparse),
+    }
+
+    let circle = Shape::Circle { r: Some(2) };
+    let result = to_bytes_with_config(&circle, bitmap).unwrap();
+    assert_eq!(result, to_bytes(&circle).unwrap());
+    assert_eq!(
+        from_bytes_with_config::<Shape>(&result, bitmap).unwrap(),
+        circle
+    );
+    let sparse = |a| Sparse {
+        a,
+        b: 1,
+        c: None,
+        d: Some(vec![None]),
+        e: Some(5),
+    };
+    let test_nested = (Shape::Nested(sparse(None)), Some(sparse(Some(4))));
+    let result = to_bytes_with_config(&test_nested, bitmap).unwrap();
+    let back: (Shape, Option<Sparse>) = from_bytes_with_config(&result, bitmap).unwrap();
+    assert_eq!(back, test_nested);
+    let mut written = Vec::new();
+    to_writer_with_config(&mut written, &test_nested, bitmap).unwrap();
+    assert_eq!(written, result);
+
+    // Streaming to a writer produces the same bytes as encoding in memory
+    let configs = [
+        Config::new(),
+        Config::new().layout(Layout::Packed),
+        Config::new().seq_length(SeqLength::Elements),
+        Config::new().option_bitmap(true),
+    ];
+    for config in configs {
+        let mut written = Vec::new();
+        to_writer_with_config(&mut written, &test_record, config).unwrap();
+        assert_eq!(written, to_bytes_with_config(&test_record, config).unwrap());
+    }
+    let mut full = [0u8; 8];
+    let result = to_writer_with_config(&mut &mut full[..], &test_record, Config::new());
+    assert!(matches!(result, Err(Error::Io(err)) if err.kind() == std::io::ErrorKind::WriteZero));
+    let mut written = Vec::new();
+    to_writer_with_config(&mut written, &test_firmware, c_layout).unwrap();
+    assert_eq!(
+        written,
+        to_bytes_with_config(&test_firmware, c_layout).unwrap()
+    );
+
+    // Decoding from a reader consumes exactly one value
+    let other_record = Record {
+        a: (7, 8),
+        b: StructVariant::B,
+        c: NewTypeVariant::A(9),
+        d: vec![],
+    };
+    for config in configs {
+        let mut written = Vec::new();
+        to_writer_with_config(&mut written, &test_record, config).unwrap();
+        to_writer_with_config(&mut written, &other_record, config).unwrap();
+        let mut reader = written.as_slice();
+        let back: Record = from_reader_with_config(&mut reader, config).unwrap();
+        assert_eq!(back, test_record);
+        let back: Record = from_reader_with_config(&mut reader, config).unwrap();
+        assert_eq!(back, other_record);
+        assert!(reader.is_empty());
+    }
+    let mut reader = &framed[..framed.len() - 1];
+    assert!(from_reader_with_config::<_, Record>(&mut reader, Config::new()).is_err());
+
+    // The size is known without encoding
+    for config in configs {
+        let size = serialized_size_with_config(&test_record, config).unwrap();
+        assert_eq!(
+            size,
+            to_bytes_with_config(&test_record, config).unwrap().len()
+        );
+    }
+    let size = serialized_size_with_config(&test_firmware, c_layout).unwrap();
+    assert_eq!(size, std::mem::size_of::<Firmware>());
+
+    // Encoding into a fixed buffer reports how much of it was used
+    let mut slice = [0u8; 64];
+    for config in configs {
+        let expected = to_bytes_with_config(&test_record, config).unwrap();
+        let len = to_slice_with_config(&test_record, &mut slice, config).unwrap();
+        assert_eq!(&slice[..len], &expected[..]);
+        let result = to_slice_with_config(&test_record, &mut slice[..len - 1], config);
+        assert!(matches!(result, Err(Error::BufferFull)));
+    }
+    let test_small_map = HashMap::from([(1u8, 2u8)]);
+    let result = to_slice_with_config(&test_small_map, &mut slice[..2], Config::new());
+    assert!(matches!(result, Err(Error::BufferFull)));
+
+    // One serializer appends many values into the same buffer, and recovers
+    // from a failed encode
+    let ser = BytesSerializer::with_config(c_layout);
+    let mut appended = vec![0xaa];
+    ser.serialize_into(&test_firmware, &mut appended).unwrap();
+    assert!(ser.serialize_into(&test_vec, &mut appended).is_err());
+    assert_eq!(appended.len(), 1 + std::mem::size_of::<Firmware>());
+    ser.serialize_into(&test_firmware, &mut appended).unwrap();
+    let de: BytesDeserializer<SliceInput> = BytesDeserializer::from_slice(&appended[1..], c_layout);
+    for _ in 0..2 {
+        let back: Firmware = de.deserialize().unwrap();
+        assert_eq!(back, test_firmware);
+    }
+    assert_eq!(de.position(), appended.len() - 1);
+
+    // Concatenated records decode one at a time
+    let mut log = Vec::new();
+    for record in [&test_record, &other_record, &test_record] {
+        to_bytes_into(record, &mut log).unwrap();
+    }
+    let records: Vec<Record> = iter_from_bytes(&log).collect::<Result<_, _>>().unwrap();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[1], other_record);
+    let mut records: RecordIter<ReaderInput<&[u8]>, Record> =
+        iter_from_reader(&log[..log.len() - 1]);
+    assert!(records.next().unwrap().is_ok());
+    assert!(records.next().unwrap().is_ok());
+    assert!(matches!(records.next(), Some(Err(Error::UnexpectedEof))));
+    assert!(records.next().is_none());
+
+    // Partial input asks for exactly as many bytes as the frame needs
+    let mut decoder = IncrementalDecoder::new();
+    let mut needed = Vec::new();
+    let mut decoded = Vec::new();
+    for chunk in log.chunks(3) {
+        decoder.feed(chunk);
+        loop {
+            match decoder.decode::<Record>().unwrap() {
+                Progress::NeedMore(n) => {
+                    needed.push(n);
+                    break;
+                }
+                Progress::Done(record) => decoded.push(record),
+            }
+        }
+    }
+    assert_eq!(decoded.len(), 3);
+    assert_eq!(decoded[1], other_record);
+    assert_eq!(needed[0], 1);
+    assert_eq!(decoder.buffered(), 0);
+    let mut decoder = IncrementalDecoder::new();
+    decoder.feed(&[9, 0, 0, 0, 1, 0, 0, 0, 7, 2, 0, 0, 0]);
+    let result = decoder.decode::<(HashMap<u8, u32>,)>();
+    assert!(matches!(result, Err(Error::InvalidData)));
+    let described = Config::new().self_describing(true);
+    let mut decoder = IncrementalDecoder::with_config(described);
+    let bytes = to_bytes_with_config(&other_record, described).unwrap();
+    decoder.feed(&bytes[..3]);
+    assert!(matches!(
+        decoder.decode::<Record>(),
+        Ok(Progress::NeedMore(2))
+    ));
+    decoder.feed(&bytes[3..]);
+    assert!(matches!(decoder.decode::<Record>(), Ok(Progress::Done(r)) if r == other_record));
+    let mut decoder = IncrementalDecoder::with_config(packed);
+    decoder.feed(&to_bytes_with_config(&other_record, packed).unwrap());
+    assert!(matches!(
+        decoder.decode::<Record>(),
+        Err(Error::Unimplemented)
+    ));
+
+    // Framed values can be skipped without knowing their type
+    let skipped = to_bytes(&(7u32, &test_record, "skipped", 9u8)).unwrap();
+    let (first, _, _, last): (u32, IgnoredAny, IgnoredAny, u8) = from_bytes(&skipped).unwrap();
+    assert_eq!((first, last), (7, 9));
+    let result = from_bytes::<(IgnoredAny, u8)>(&to_bytes(&(1u8, 2u8)).unwrap());
+    assert!(result.is_err());
+    for config in [packed, counted] {
+        let bytes = to_bytes_with_config(&(vec![5u32, 6], 7u8), config).unwrap();
+        let result = from_bytes_with_config::<(IgnoredAny, u8)>(&bytes, config);
+        assert!(matches!(result, Err(Error::Unimplemented)));
+    }
+
+    // Structs can gain trailing fields in the framed layout
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct VersionOne {
+        id: u32,
+        name: String,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct VersionTwo {
+        id: u32,
+        name: String,
+        #[serde(default)]
+        tags: Vec<u16>,
+        #[serde(default)]
+        parent: Option<u32>,
+    }
+
+    let old = VersionOne {
+        id: 1,
+        name: "one".to_string(),
+    };
+    let new = VersionTwo {
+        id: 2,
+        name: "two".to_string(),
+        tags: vec![3, 4],
+        parent: Some(1),
+    };
+    let back: VersionTwo = from_bytes(&to_bytes(&old).unwrap()).unwrap();
+    assert_eq!((back.id, back.tags, back.parent), (1, vec![], None));
+    let nested = to_bytes(&(&new, 5u8)).unwrap();
+    let (back, last): (VersionOne, u8) = from_bytes(&nested).unwrap();
+    assert_eq!((back.id, back.name.as_str(), last), (2, "two", 5));
+    let bytes = to_bytes_with_config(&new, described).unwrap();
+    let back: VersionOne = from_bytes_with_config(&bytes, described).unwrap();
+    assert_eq!((back.id, back.name.as_str()), (2, "two"));
+
+    // Tuples and arrays must match their frame exactly
+    let result = from_bytes::<(u8, u8)>(&to_bytes(&(1u8, 2u8, 3u8)).unwrap());
+    assert!(matches!(result, Err(Error::InvalidData)));
+    let result = from_bytes::<[u8; 2]>(&to_bytes(&[1u8, 2, 3]).unwrap());
+    assert!(matches!(result, Err(Error::InvalidData)));
+
+    // Types that inspect the data need the self-describing mode
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[serde(tag = "kind")]
+    enum Event {
+        Started { at: u64 },
+        Stopped { at: u64, code: i32 },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[serde(untagged)]
+    enum Scalar {
+        Int(i64),
+        Text(String),
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Envelope {
+        id: u32,
+        events: Vec<Event>,
+        scalars: Vec<Scalar>,
+        #[serde(flatten)]
+        extra: HashMap<String, u16>,
+    }
+
+    let test_envelope = Envelope {
+        id: 1,
+        events: vec![
+            Event::Started { at: 10 },
+            Event::Stopped { at: 20, code: -1 },
+        ],
+        scalars: vec![Scalar::Int(-5), Scalar::Text("five".to_string())],
+        extra: HashMap::from([("retries".to_string(), 3)]),
+    };
+    let result = to_bytes_with_config(&test_envelope, described).unwrap();
+    let back: Envelope = from_bytes_with_config(&result, described).unwrap();
+    assert_eq!(back, test_envelope);
+    let plain = to_bytes(&test_envelope).unwrap();
+    assert!(from_bytes::<Envelope>(&plain).is_err());
+    let result = to_bytes_with_config(&test_record, described).unwrap();
+    let back: Record = from_bytes_with_config(&result, described).unwrap();
+    assert_eq!(back, test_record);
+    let value: serde_json::Value = from_bytes_with_config(&result, described).unwrap();
+    assert_eq!(value["b"]["A"]["b"], 3);
+    assert_eq!(value["c"]["A"], 4);
+    let back: Record =
+        from_bytes_with_config(&to_bytes_with_config(&value, described).unwrap(), described)
+            .unwrap();
+    assert_eq!(back, test_record);
+
+    // Canonical encoding sorts map keys and set elements
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Index {
+        names: HashMap<String, u32>,
+        #[serde(with = "transform::canonical_set")]
+        tags: HashSet<u16>,
+    }
+
+    let canonical = Config::new().canonical(true);
+    let test_index = Index {
+        names: (0..32).map(|i| (format!("name{}", i), i)).collect(),
+        tags: (0..32).collect(),
+    };
+    let mut reordered = Index {
+        names: HashMap::new(),
+        tags: HashSet::new(),
+    };
+    for i in (0..32).rev() {
+        reordered.names.insert(format!("name{}", i), i);
+        reordered.tags.insert(i as u16);
+    }
+    for config in [canonical, canonical.self_describing(true)] {
+        let result = to_bytes_with_config(&test_index, config).unwrap();
+        assert_eq!(result, to_bytes_with_config(&reordered, config).unwrap());
+        let back: Index = from_bytes_with_config(&result, config).unwrap();
+        assert_eq!(back, test_index);
+    }
+    // A seq of unframed pairs has the same layout as a map
+    let packed = Config::new().layout(Layout::Packed);
+    let unsorted = to_bytes_with_config(&vec![(2u8, 0u8), (1, 0)], packed).unwrap();
+    let sorted = to_bytes_with_config(&vec![(1u8, 0u8), (2, 0)], packed).unwrap();
+    let packed_canonical = packed.canonical(true);
+    assert!(from_bytes_with_config::<HashMap<u8, u8>>(&sorted, packed_canonical).is_ok());
+    assert!(from_bytes_with_config::<HashMap<u8, u8>>(&unsorted, packed_canonical).is_err());
+    assert!(from_bytes_with_config::<HashMap<u8, u8>>(&unsorted, packed).is_ok());
+
+    // Single fields can be read without decoding the rest of the value
+    #[derive(Serialize, Deserialize, BinLayout, Debug, PartialEq)]
+    struct Reading {
+        sensor: u32,
+        at: (u16, u8),
+        value: f64,
+    }
+
+    let test_readings = vec![
+        Reading {
+            sensor: 7,
+            at: (12, 30),
+            value: 0.5,
+        },
+        Reading {
+            sensor: 8,
+            at: (13, 45),
+            value: -1.0,
+        },
+    ];
+    let result = to_bytes(&test_readings).unwrap();
+    let frame = Frame::new(&result).unwrap();
+    let second = frame.element_of::<Reading>(1).unwrap().unwrap();
+    let sensor = second.field::<Reading>(0).unwrap().unwrap();
+    assert_eq!(sensor.decode::<u32>().unwrap(), 8);
+    let value = second.field::<Reading>(2).unwrap().unwrap();
+    assert_eq!(value.decode::<f64>().unwrap(), -1.0);
+    let at = second.field::<Reading>(1).unwrap().unwrap();
+    assert_eq!(at.decode::<(u16, u8)>().unwrap(), (13, 45));
+    assert!(second.field::<Reading>(3).unwrap().is_none());
+    assert!(frame.element_of::<Reading>(2).unwrap().is_none());
+    assert!(matches!(
+        Frame::new(&framed).unwrap().element(0),
+        Err(Error::Unimplemented)
+    ));
+    assert!(matches!(
+        Frame::with_config(&result, packed),
+        Err(Error::Unimplemented)
+    ));
+    let levels = std::collections::BTreeMap::from([(1u16, 10u32), (2, 20)]);
+    let result = to_bytes(&levels).unwrap();
+    let frame = Frame::new(&result).unwrap();
+    let (key, value) = frame.entry_of::<u16, u32>(1).unwrap().unwrap();
+    assert_eq!(key.decode::<u16>().unwrap(), 2);
+    assert_eq!(value.decode::<u32>().unwrap(), 20);
+    assert!(frame.entry_of::<u16, u32>(2).unwrap().is_none());
+    assert!(matches!(frame.entry(0), Err(Error::Unimplemented)));
+    let result = to_bytes_with_config(&test_envelope, described).unwrap();
+    let frame = Frame::with_config(&result, described).unwrap();
+    let (key, value) = frame.entry(1).unwrap().unwrap();
+    assert_eq!(key.decode::<&str>().unwrap(), "events");
+    let event: Event = value.element(1).unwrap().unwrap().decode().unwrap();
+    assert_eq!(event, test_envelope.events[1]);
+    let only = HashMap::from([("only".to_string(), vec![1u8, 2])]);
+    let result = to_bytes_with_config(&only, described).unwrap();
+    let frame = Frame::with_config(&result, described).unwrap();
+    let (key, value) = frame.entry(0).unwrap().unwrap();
+    assert_eq!(key.decode::<&str>().unwrap(), "only");
+    assert_eq!(value.decode::<Vec<u8>>().unwrap(), [1, 2]);
+
+    // Borrowed strings point into the mapped file
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Snapshot<'a> {
+        name: &'a str,
+        owner: String,
+        initial: char,
+        values: Vec<u32>,
+    }
+
+    let test_snapshot = Snapshot {
+        name: "snapshot",
+        owner: "vm".to_string(),
+        initial: 'é',
+        values: vec![1, 2, 3],
+    };
+    let path = std::env::temp_dir().join(format!("transform-vm-{}.bin", std::process::id()));
+    std::fs::write(&path, to_bytes(&test_snapshot).unwrap()).unwrap();
+    // SAFETY: nothing changes the file while it is mapped
+    let mapped = unsafe { MappedFile::open(&path) }.unwrap();
+    let back: Snapshot = mapped.decode().unwrap();
+    assert_eq!(back, test_snapshot);
+    assert!(mapped.bytes().as_ptr_range().contains(&back.name.as_ptr()));
+    let mut truncated = mapped.bytes().to_vec();
+    truncated.pop();
+    drop(mapped);
+    std::fs::write(&path, &truncated).unwrap();
+    // SAFETY: as above
+    let mapped = unsafe { MappedFile::open(&path) }.unwrap();
+    assert!(matches!(
+        mapped.decode::<Snapshot>(),
+        Err(Error::UnexpectedEof)
+    ));
+    drop(mapped);
+    // SAFETY: as above
+    let mapped = unsafe { MappedFile::open_with_config(&path, packed) }.unwrap();
+    assert!(matches!(
+        mapped.decode::<Snapshot>(),
+        Err(Error::Unimplemented)
+    ));
+    drop(mapped);
+    std::fs::write(
+        &path,
+        to_bytes_with_config(&test_snapshot, described).unwrap(),
+    )
+    .unwrap();
+    // SAFETY: as above
+    let mapped = unsafe { MappedFile::open_with_config(&path, described) }.unwrap();
+    assert_eq!(mapped.decode::<Snapshot>().unwrap(), test_snapshot);
+    drop(mapped);
+    std::fs::remove_file(&path).unwrap();
+
+    // Forwarding a body without decoding it
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Message<B> {
+        topic: String,
+        body: B,
+    }
+
+    let message = Message {
+        topic: "records".to_string(),
+        body: &test_record,
+    };
+    let bytes = to_bytes(&message).unwrap();
+    let routed: Message<Raw> = from_bytes(&bytes).unwrap();
+    assert_eq!(routed.topic, "records");
+    assert!(bytes
+        .as_ptr_range()
+        .contains(&routed.body.as_bytes().as_ptr()));
+    let body: Record = from_bytes(routed.body.as_bytes()).unwrap();
+    assert_eq!(body, test_record);
+    assert_eq!(to_bytes(&routed).unwrap(), bytes);
+    let owned: Message<RawBuf> = from_reader_with_config(&bytes[..], Config::new()).unwrap();
+    assert_eq!(owned.body.as_raw(), routed.body);
+    assert_eq!(to_bytes(&owned).unwrap(), bytes);
+
+    let config = Config::new().self_describing(true);
+    let message = Message {
+        topic: "numbers".to_string(),
+        body: vec![1u8, 2, 3],
+    };
+    let bytes = to_bytes_with_config(&message, config).unwrap();
+    let routed: Message<Raw> = from_bytes_with_config(&bytes, config).unwrap();
+    let body: Vec<u8> = from_bytes_with_config(routed.body.as_bytes(), config).unwrap();
+    assert_eq!(body, message.body);
+    assert_eq!(to_bytes_with_config(&routed, config).unwrap(), bytes);
+    let bytes = to_bytes_with_config(&message, packed).unwrap();
+    let result = from_bytes_with_config::<Message<Raw>>(&bytes, packed);
+    assert!(matches!(result, Err(Error::Unimplemented)));
+
+    // Loading fixed-size records from the front of a buffer
+    let mut bytes = to_bytes(&(7u32, 9u16)).unwrap();
+    bytes.extend_from_slice(&[1, 2]);
+    let value: (u32, u16) = load(&bytes).unwrap();
+    assert_eq!(value, (7, 9));
+    let (value, rest) = load_with_rest::<(u32, u16)>(&bytes).unwrap();
+    assert_eq!(value, (7, 9));
+    assert_eq!(rest, [1, 2]);
+    assert!(matches!(
+        load::<(u32, u16)>(&bytes[..5]),
+        Err(Error::UnexpectedEof)
+    ));
+    fn check_size<T: FixedSize + Serialize>(value: T) {
+        assert_eq!(serialized_size(&value).unwrap(), T::SIZE);
+    }
+    check_size(7u64);
+    check_size('x');
+    check_size(());
+    check_size([1u16, 2, 3]);
+    check_size((true, -1i8, 2.5f32, [[0u8; 2]; 3]));
+
+    // Field offsets of fixed-size structs
+    #[derive(Serialize, Deserialize, BinLayout, Debug, PartialEq)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Serialize, Deserialize, BinLayout, Debug, PartialEq)]
+    struct Id(u64);
+
+    #[derive(Serialize, Deserialize, BinLayout, Debug, PartialEq)]
+    struct Sample<T> {
+        id: Id,
+        flag: bool,
+        at: Point,
+        value: T,
+    }
+
+    let sample = Sample {
+        id: Id(42),
+        flag: true,
+        at: Point { x: -3, y: 5 },
+        value: 1.5f64,
+    };
+    let bytes = to_bytes(&sample).unwrap();
+    assert_eq!(bytes.len(), Sample::<f64>::SIZE);
+    assert_eq!(Id::SIZE, 8);
+    let names: Vec<_> = Sample::<f64>::FIELDS
+        .iter()
+        .map(|field| field.name)
+        .collect();
+    assert_eq!(names, ["id", "flag", "at", "value"]);
+
+    #[derive(Serialize, BinLayout)]
+    struct Keyword {
+        r#type: u8,
+    }
+    let field = Keyword::FIELDS[0];
+    assert_eq!(field.name, "type");
+    let keyword = to_bytes(&Keyword { r#type: 3 }).unwrap();
+    assert_eq!(load::<u8>(&keyword[field.offset..]).unwrap(), 3);
+    let at = Sample::<f64>::FIELDS[2];
+    assert_eq!((at.offset, at.size), (13, Point::SIZE));
+    let point: Point = load(&bytes[at.offset..]).unwrap();
+    assert_eq!(point, sample.at);
+    let y = Point::FIELDS[1];
+    let y: i32 = load(&bytes[at.offset + y.offset..]).unwrap();
+    assert_eq!(y, 5);
+    let value = Sample::<f64>::FIELDS[3];
+    assert_eq!(value.offset + value.size, bytes.len());
+    assert_eq!(load::<Sample<f64>>(&bytes).unwrap(), sample);
+
+    // Tables of fixed-size records
+    let mut writer = TableWriter::with_header::<Point>(Vec::new()).unwrap();
+    for i in 0..4 {
+        writer.append(&Point { x: i, y: -i }).unwrap();
+    }
+    assert!(writer.append(&7u8).is_err());
+    assert_eq!(writer.len(), 4);
+    let mut table = writer.into_inner();
+    let mut more = TableWriter::new(&mut table);
+    more.append(&Point { x: 4, y: -4 }).unwrap();
+    let reader = TableReader::<Point>::with_header(&table).unwrap();
+    assert_eq!(reader.len(), 5);
+    assert_eq!(reader.get(3).unwrap(), Some(Point { x: 3, y: -3 }));
+    assert_eq!(reader.get(5).unwrap(), None);
+    let points: Vec<Point> = reader.iter().collect::<Result<_, _>>().unwrap();
+    assert_eq!(points.len(), 5);
+    assert_eq!(points[4], Point { x: 4, y: -4 });
+    assert!(TableReader::<Id>::with_header(&table).is_err());
+    mod moved {
+        #[derive(serde::Deserialize, transform::BinLayout, Debug, PartialEq)]
+        pub struct Point {
+            pub x: i32,
+            pub y: i32,
+        }
+    }
+    let moved = TableReader::<moved::Point>::with_header(&table).unwrap();
+    assert_eq!(moved.get(4).unwrap(), Some(moved::Point { x: 4, y: -4 }));
+    #[derive(Deserialize, BinLayout, Debug, PartialEq)]
+    struct Swapped {
+        y: i32,
+        x: i32,
+    }
+    assert!(TableReader::<Swapped>::with_header(&table).is_err());
+    let swapped = TableReader::<Swapped>::new(&table[HEADER_SIZE..]).unwrap();
+    assert_eq!(swapped.get(4).unwrap(), Some(Swapped { y: 4, x: -4 }));
+    mod retyped {
+        #[derive(serde::Deserialize, transform::BinLayout)]
+        pub struct Point {
+            pub x: f32,
+            pub y: i32,
+        }
+    }
+    assert!(TableReader::<retyped::Point>::with_header(&table).is_err());
+    let retyped = TableReader::<retyped::Point>::new(&table[HEADER_SIZE..]).unwrap();
+    let point = retyped.get(4).unwrap().unwrap();
+    assert_eq!((point.x.to_bits(), point.y), (4, -4));
+    let path = std::env::temp_dir().join(format!("transform-vm-{}.table", std::process::id()));
+    std::fs::write(&path, &table).unwrap();
+    let open = || {
+        std::fs::OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(&path)
+            .unwrap()
+    };
+    assert!(TableWriter::append_with_header::<Swapped>(open()).is_err());
+    let mut more = TableWriter::append_with_header::<Point>(open()).unwrap();
+    more.append(&Point { x: 5, y: -5 }).unwrap();
+    drop(more);
+    let appended = std::fs::read(&path).unwrap();
+    let reader = TableReader::<Point>::with_header(&appended).unwrap();
+    assert_eq!(reader.get(5).unwrap(), Some(Point { x: 5, y: -5 }));
+    std::fs::remove_file(&path).unwrap();
+    assert!(TableReader::<Point>::with_header(&table[..table.len() - 1]).is_err());
+    let reader = TableReader::<u16>::new(&[1, 0, 2, 0]).unwrap();
+    assert_eq!(reader.iter().len(), 2);
+    assert_eq!(reader.get(1).unwrap(), Some(2));
+
+    // Values framed over an in-memory stream
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let (client, server) = tokio::io::duplex(16);
+        let mut client = Framed::new(client, TransformCodec::<Record>::new());
+        let mut server = Framed::new(server, TransformCodec::<Record>::new());
+        let sent = [test_record, other_record];
+        let writer = async {
+            for record in &sent {
+                client.send(record).await.unwrap();
+            }
+            client
+        };
+        let reader = async {
+            let mut received = Vec::new();
+            while received.len() < sent.len() {
+                received.push(server.next().await.unwrap().unwrap());
+            }
+            received
+        };
+        let (_client, received) = tokio::join!(writer, reader);
+        assert_eq!(received, sent);
+
+        let (client, server) = tokio::io::duplex(64);
+        let mut client = FramedWrite::new(client, TransformCodec::<Record>::new());
+        let small = TransformCodec::<Record>::new().max_frame_size(8);
+        let mut server = FramedRead::new(server, small);
+        client.send(&sent[0]).await.unwrap();
+        assert!(matches!(
+            server.next().await,
+            Some(Err(Error::FrameTooLarge(_)))
+        ));
+
+        let mut body = to_bytes(&sent[0]).unwrap();
+        body.push(0);
+        let mut frame = (body.len() as u32).to_le_bytes().to_vec();
+        frame.extend_from_slice(&body);
+        let mut server = FramedRead::new(&frame[..], TransformCodec::<Record>::new());
+        assert!(matches!(server.next().await, Some(Err(Error::InvalidData))));
+
+        let mut stream = Vec::new();
+        for record in &sent {
+            to_async_writer(&mut stream, record).await.unwrap();
+        }
+        let mut reader = &stream[..];
+        for record in &sent {
+            let back: Record = from_async_reader(&mut reader).await.unwrap();
+            assert_eq!(&back, record);
+        }
+        assert!(reader.is_empty());
+        let result = from_async_reader::<_, Record>(&stream[..stream.len() / 2 - 1]).await;
+        assert!(matches!(result, Err(Error::UnexpectedEof)));
+        let result = from_async_reader_with_config::<_, Record>(&stream[..], packed).await;
+        assert!(matches!(result, Err(Error::Unimplemented)));
+
+        let mut stream = Vec::new();
+        for record in &sent {
+            to_async_writer_with_config(&mut stream, record, described)
+                .await
+                .unwrap();
+        }
+        let mut reader = &stream[..];
+        for record in &sent {
+            let back: Record = from_async_reader_with_config(&mut reader, described)
+                .await
+                .unwrap();
+            assert_eq!(&back, record);
+        }
+        assert!(reader.is_empty());
+    });
 }